@@ -0,0 +1,995 @@
+// the state machine as it was before the states became an enum , every state is a boxed
+// State and every character makes a new one , it is only kept so benches/machine.rs has
+// something to compare the ImagineMachine with , the bench doesn't use all of it
+
+#![allow(dead_code , clippy::upper_case_acronyms)]
+
+use imagine_core::common::{Errors , LexError , LexErrorKind , NumberError , Position , Span};
+
+#[derive(PartialEq , Debug)]
+pub enum Token {
+    Number(i32),
+    Operator(String),
+    Float(f32),
+    Ident(String),
+    Keyword(String),
+    LPAR,
+    RPAR,
+    LBR,
+    RBR,
+    // string literals
+    Text(String),
+    // only emitted if the machine is asked to keep comments
+    Comment(String),
+    // text the lexer could not make sense of (see Lexer::lex_recovering)
+    Error(String),
+    Blank,
+}
+
+// a token together with the part of the source text it came from
+#[derive(PartialEq , Debug)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+fn is_operator(input: &char) -> bool {
+    ['+', '-' , '=' , '/' , '*' , '!' , '<' , '>' , '~' , '|' , '&' , '^'].contains(input)
+}
+
+// every transition returns the new state and optinaly a token
+pub type Update = (Box<dyn State> , Option<Token>);
+
+const KEYWORD_LIST: &[&str] = &["if"];
+
+// represents the state of the current token being processed
+// get_type and get_value methods are used for debugging and testing
+pub trait State {
+
+    // returns the new state to transition and optinaly a token if everything went ok
+    // or the kind of error , the machine adds the position and the offending character
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind>;
+    fn get_type(&self) -> String;
+    fn get_token(&self) -> Token;
+
+    // called when the input ends while in this state
+    // states that can't end a token (like an unclosed string) return an error
+    fn get_final_token(&self) -> Result<Token , LexErrorKind> {
+        Ok(self.get_token())
+    }
+
+    // only used for testing
+    fn get_value(&self) -> String {
+        String::from("")
+    }
+
+}
+
+// input handling and transition and token emitting logic is implemented
+// un structs that implement the State trait
+
+// used for all the single/double character operators
+// + , - , * , / , == and etc.
+
+struct Lpar;
+struct Rpar;
+struct Lbr;
+struct Rbr;
+
+impl State for Lpar {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+
+        if input.is_whitespace() || input == '\n'  {
+            let new_state = Box::new(Blank {});
+            let token = self.get_token();
+            return Ok((new_state , Some(token)));
+        }
+        
+        let new_state = (Blank {}).feed(input)?.0;
+        let token = self.get_token();
+        Ok((new_state , Some(token)))
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::LPAR
+    }
+
+    fn get_type(&self) -> String {
+        String::from("Lpar")
+    }
+
+    fn get_value(&self) -> String {
+        String::from("(")
+    }
+
+}
+
+impl State for Rpar {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+
+        if input.is_whitespace() || input == '\n'  {
+            let new_state = Box::new(Blank {});
+            let token = self.get_token();
+            return Ok((new_state , Some(token)));
+        }
+        
+        let new_state = (Blank {}).feed(input)?.0;
+        let token = self.get_token();
+        Ok((new_state , Some(token)))
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::RPAR
+    }
+
+    fn get_type(&self) -> String {
+        String::from("Rpar")
+    }
+
+    fn get_value(&self) -> String {
+        String::from(")")
+    }
+
+}
+
+impl State for Lbr {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+
+        if input.is_whitespace() || input == '\n'  {
+            let new_state = Box::new(Blank {});
+            let token = self.get_token();
+            return Ok((new_state , Some(token)));
+        }
+        
+        let new_state = (Blank {}).feed(input)?.0;
+        let token = self.get_token();
+        Ok((new_state , Some(token)))
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::LBR
+    }
+
+    fn get_type(&self) -> String {
+        String::from("Lbr")
+    }
+
+    fn get_value(&self) -> String {
+        String::from("{")
+    }
+
+}
+
+impl State for Rbr {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+
+        if input.is_whitespace() || input == '\n'  {
+            let new_state = Box::new(Blank {});
+            let token = self.get_token();
+            return Ok((new_state , Some(token)));
+        }
+        
+        let new_state = (Blank {}).feed(input)?.0;
+        let token = self.get_token();
+        Ok((new_state , Some(token)))
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::RBR
+    }
+
+    fn get_type(&self) -> String {
+        String::from("Rbr")
+    }
+
+    fn get_value(&self) -> String {
+        String::from("}")
+    }
+
+}
+
+struct Operator {
+    value: String,
+}
+
+impl Operator {
+    fn new(value: String) -> Operator {
+        Operator { value }
+    }       
+}
+
+impl State for Operator {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+
+        // a / followed by another / or a * starts a comment instead
+        if self.value == "/" && input == '/' {
+            let new_state = Box::new(LineComment::new(String::from("//")));
+            return Ok((new_state , None));
+        }
+
+        if self.value == "/" && input == '*' {
+            let new_state = Box::new(BlockComment::new(String::from("/*") , 1 , None));
+            return Ok((new_state , None));
+        }
+
+        if is_operator(&input) {
+            let new_operator = format!("{}{}" , self.value , input);
+            let new_state = Box::new(Operator::new(new_operator));
+            return Ok((new_state , None));
+        }
+
+        if input.is_whitespace() || input == '\n'  {
+            let new_state = Box::new(Blank {});
+            let token = self.get_token();
+            return Ok((new_state , Some(token)));
+        }
+        
+        let new_state = (Blank {}).feed(input)?.0;
+        let token = self.get_token();
+        Ok((new_state , Some(token)))
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::Operator(self.value.clone())
+    }
+
+    fn get_type(&self) -> String {
+        String::from("Operator")
+    }
+
+    fn get_value(&self) -> String {
+        self.value.clone()
+    }
+
+}
+
+// idents are varible names or keywords
+struct Ident {
+    value: String,
+    is_keyword: bool,
+}
+
+impl Ident {
+    fn new(value: String , is_keyword: bool) -> Ident {
+        Ident { value , is_keyword }
+    }
+}
+
+impl State for Ident {
+    
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+
+        // idents can state with only alphabetic characters (see the blank state)
+        // and can contain _ or alphanumerical characters
+        if input.is_alphanumeric() || input == '_' {
+
+            let new_value = format!("{}{}" , self.value , input);
+
+            if KEYWORD_LIST.contains(&new_value.as_str()) {
+                let new_state = Box::new(Ident::new(new_value , true));
+                return Ok((new_state , None));
+            }
+
+            let new_state = Box::new(Ident::new(new_value , false)); 
+            return Ok((new_state , None));
+
+        }
+
+        if is_operator(&input) {
+
+            let new_state = Box::new(Operator::new(String::from(input)));
+            let token = self.get_token();
+            return Ok((new_state , Some(token)));
+
+        }
+
+        if ['(' ,')' , '{' , '}'].contains(&input) {
+
+            let new_state = (Blank {}).feed(input)?.0;
+            let token = self.get_token();
+            return Ok((new_state , Some(token)))
+
+        }
+        
+        if input.is_whitespace() || input == '\n'  {
+            let new_state = Box::new(Blank {});
+            let token = self.get_token();
+            return Ok((new_state , Some(token)));
+        }
+
+        Err(LexErrorKind::UnexpectedCharacter)
+
+    }
+
+    fn get_token(&self) -> Token {
+
+        // while mabey not the best solution it works
+
+        if self.is_keyword {
+            return Token::Keyword(self.value.clone());
+        }
+
+        Token::Ident(self.value.clone())
+
+    }
+
+    fn get_type(&self) -> String {
+        if self.is_keyword {String::from("Keyword")} else {String::from("Ident")}
+    }
+
+    fn get_value(&self) -> String {
+        self.value.clone()
+    }
+
+}
+
+// used for float numbers like 3.14
+struct Float {
+    value: String,
+}
+
+impl Float {
+    fn new(value: String) -> Float {
+        Float {value}
+    }
+}
+
+impl State for Float {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+        
+        if input.is_ascii_digit() {
+            let new_value = format!("{}{}" , self.value , input);
+            let new_state = Box::new(Float::new(new_value)); 
+            return Ok((new_state , None));
+        }
+
+        if input.is_whitespace() || input == '\n'  {
+            let new_state = Box::new(Blank {});
+            let token = self.get_token();
+            return Ok((new_state , Some(token)));
+        }
+
+        if input.is_alphanumeric() || input == '_' || input == '.' {
+            return Err(LexErrorKind::MalformedNumber(NumberError::InvalidSuffix));
+        }
+
+        Err(LexErrorKind::UnexpectedCharacter)
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::Float(self.value.parse::<f32>().unwrap())
+    }
+
+    fn get_value(&self) -> String {
+        self.value.clone()
+    }
+
+    fn get_type(&self) -> String {
+        String::from("Float")
+    }
+
+}
+
+// used for i32 numbers
+#[derive(PartialEq , Debug)]
+struct Number {
+    value: String,
+}
+
+impl Number {
+
+    fn new(value: String) -> Number {
+        Number {value}
+    }
+
+}
+
+impl State for Number {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+        
+        if input.is_ascii_digit() {
+
+            let new_value = format!("{}{}" , self.value , input);
+
+            // catch the overflow here so get_token can't fail
+            if new_value.parse::<i32>().is_err() {
+                return Err(LexErrorKind::IntegerOverflow);
+            }
+
+            let new_state = Box::new(Number::new(new_value)); 
+            return Ok((new_state , None));
+
+        }
+
+        if input == '.' {
+            let new_value = format!("{}." , self.value);
+            let new_state = Box::new(Float::new(new_value)); 
+            return Ok((new_state , None));
+        }
+
+        if ['(' ,')' , '{' , '}'].contains(&input) {
+
+            let new_state = (Blank {}).feed(input)?.0;
+            let token = self.get_token();
+            return Ok((new_state , Some(token)))
+
+        }
+        
+        if input.is_whitespace() || input == '\n'  {
+            let new_state = Box::new(Blank {});
+            let token = self.get_token();
+            return Ok((new_state , Some(token)));
+        }
+
+        if input.is_alphabetic() || input == '_' {
+            return Err(LexErrorKind::MalformedNumber(NumberError::InvalidSuffix));
+        }
+
+        Err(LexErrorKind::UnexpectedCharacter)
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::Number(self.value.parse::<i32>().unwrap())
+    }
+
+    fn get_type(&self) -> String {
+        String::from("Number")
+    }
+
+    // only used for testing
+    fn get_value(&self) -> String {
+        self.value.clone()
+    }
+
+}
+
+// used for string literals like "hello\n" , value holds the text with the escapes already applied
+// strings can span multiple lines
+struct Text {
+    value: String,
+}
+
+impl Text {
+    fn new(value: String) -> Text {
+        Text { value }
+    }
+}
+
+impl State for Text {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+
+        if input == '"' {
+            let new_state = Box::new(TextEnd::new(self.value.clone()));
+            return Ok((new_state , None));
+        }
+
+        if input == '\\' {
+            let new_state = Box::new(TextEscape::new(self.value.clone()));
+            return Ok((new_state , None));
+        }
+
+        let new_value = format!("{}{}" , self.value , input);
+        let new_state = Box::new(Text::new(new_value));
+        Ok((new_state , None))
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::Text(self.value.clone())
+    }
+
+    fn get_final_token(&self) -> Result<Token , LexErrorKind> {
+        Err(LexErrorKind::UnterminatedLiteral)
+    }
+
+    fn get_type(&self) -> String {
+        String::from("Text")
+    }
+
+    fn get_value(&self) -> String {
+        self.value.clone()
+    }
+
+}
+
+// the character right after a \ in a string
+struct TextEscape {
+    value: String,
+}
+
+impl TextEscape {
+    fn new(value: String) -> TextEscape {
+        TextEscape { value }
+    }
+}
+
+impl State for TextEscape {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+
+        let escaped = match input {
+            'n' => '\n',
+            't' => '\t',
+            '\\' => '\\',
+            '"' => '"',
+            'u' => {
+                let new_state = Box::new(TextUnicode::new(self.value.clone() , None));
+                return Ok((new_state , None));
+            }
+            _ => return Err(LexErrorKind::InvalidEscape),
+        };
+
+        let new_value = format!("{}{}" , self.value , escaped);
+        let new_state = Box::new(Text::new(new_value));
+        Ok((new_state , None))
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::Text(self.value.clone())
+    }
+
+    fn get_final_token(&self) -> Result<Token , LexErrorKind> {
+        Err(LexErrorKind::UnterminatedLiteral)
+    }
+
+    fn get_type(&self) -> String {
+        String::from("TextEscape")
+    }
+
+    fn get_value(&self) -> String {
+        self.value.clone()
+    }
+
+}
+
+// a \u{...} escape , digits is None until the opening { is seen
+// it holds 1 to 6 hex digits of a unicode scalar value
+struct TextUnicode {
+    value: String,
+    digits: Option<String>,
+}
+
+impl TextUnicode {
+    fn new(value: String , digits: Option<String>) -> TextUnicode {
+        TextUnicode { value , digits }
+    }
+}
+
+impl State for TextUnicode {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+
+        let digits = match &self.digits {
+            Some(digits) => digits,
+            None if input == '{' => {
+                let new_state = Box::new(TextUnicode::new(self.value.clone() , Some(String::new())));
+                return Ok((new_state , None));
+            }
+            None => return Err(LexErrorKind::InvalidEscape),
+        };
+
+        if input.is_ascii_hexdigit() && digits.len() < 6 {
+            let new_digits = format!("{}{}" , digits , input);
+            let new_state = Box::new(TextUnicode::new(self.value.clone() , Some(new_digits)));
+            return Ok((new_state , None));
+        }
+
+        if input == '}' && !digits.is_empty() {
+
+            let escaped = u32::from_str_radix(digits , 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or(LexErrorKind::InvalidEscape)?;
+
+            let new_value = format!("{}{}" , self.value , escaped);
+            let new_state = Box::new(Text::new(new_value));
+            return Ok((new_state , None));
+
+        }
+
+        Err(LexErrorKind::InvalidEscape)
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::Text(self.value.clone())
+    }
+
+    fn get_final_token(&self) -> Result<Token , LexErrorKind> {
+        Err(LexErrorKind::UnterminatedLiteral)
+    }
+
+    fn get_type(&self) -> String {
+        String::from("TextUnicode")
+    }
+
+    fn get_value(&self) -> String {
+        self.value.clone()
+    }
+
+}
+
+// the closing " was seen , the token is emitted with the next character
+struct TextEnd {
+    value: String,
+}
+
+impl TextEnd {
+    fn new(value: String) -> TextEnd {
+        TextEnd { value }
+    }
+}
+
+impl State for TextEnd {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+
+        if input.is_whitespace() || input == '\n'  {
+            let new_state = Box::new(Blank {});
+            let token = self.get_token();
+            return Ok((new_state , Some(token)));
+        }
+
+        let new_state = (Blank {}).feed(input)?.0;
+        let token = self.get_token();
+        Ok((new_state , Some(token)))
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::Text(self.value.clone())
+    }
+
+    fn get_type(&self) -> String {
+        String::from("TextEnd")
+    }
+
+    fn get_value(&self) -> String {
+        self.value.clone()
+    }
+
+}
+
+// a // comment , it ends at the end of the line
+// value holds the whole comment including the //
+struct LineComment {
+    value: String,
+}
+
+impl LineComment {
+    fn new(value: String) -> LineComment {
+        LineComment { value }
+    }
+}
+
+impl State for LineComment {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+
+        if input == '\n' {
+            let new_state = Box::new(Blank {});
+            let token = self.get_token();
+            return Ok((new_state , Some(token)));
+        }
+
+        let new_value = format!("{}{}" , self.value , input);
+        let new_state = Box::new(LineComment::new(new_value));
+        Ok((new_state , None))
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::Comment(self.value.clone())
+    }
+
+    fn get_type(&self) -> String {
+        String::from("LineComment")
+    }
+
+    fn get_value(&self) -> String {
+        self.value.clone()
+    }
+
+}
+
+// a /* */ comment , they can be nested so depth counts the comments still open
+// last is the previous character if it may start a /* or */ pair
+struct BlockComment {
+    value: String,
+    depth: usize,
+    last: Option<char>,
+}
+
+impl BlockComment {
+    fn new(value: String , depth: usize , last: Option<char>) -> BlockComment {
+        BlockComment { value , depth , last }
+    }
+}
+
+impl State for BlockComment {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+
+        let new_value = format!("{}{}" , self.value , input);
+
+        if self.last == Some('*') && input == '/' {
+
+            if self.depth == 1 {
+                let new_state = Box::new(CommentEnd::new(new_value));
+                return Ok((new_state , None));
+            }
+
+            let new_state = Box::new(BlockComment::new(new_value , self.depth - 1 , None));
+            return Ok((new_state , None));
+
+        }
+
+        if self.last == Some('/') && input == '*' {
+            let new_state = Box::new(BlockComment::new(new_value , self.depth + 1 , None));
+            return Ok((new_state , None));
+        }
+
+        let new_state = Box::new(BlockComment::new(new_value , self.depth , Some(input)));
+        Ok((new_state , None))
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::Comment(self.value.clone())
+    }
+
+    fn get_final_token(&self) -> Result<Token , LexErrorKind> {
+        Err(LexErrorKind::UnterminatedComment)
+    }
+
+    fn get_type(&self) -> String {
+        String::from("BlockComment")
+    }
+
+    fn get_value(&self) -> String {
+        self.value.clone()
+    }
+
+}
+
+// the closing */ was seen , the token is emitted with the next character
+struct CommentEnd {
+    value: String,
+}
+
+impl CommentEnd {
+    fn new(value: String) -> CommentEnd {
+        CommentEnd { value }
+    }
+}
+
+impl State for CommentEnd {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+
+        if input.is_whitespace() || input == '\n'  {
+            let new_state = Box::new(Blank {});
+            let token = self.get_token();
+            return Ok((new_state , Some(token)));
+        }
+
+        let new_state = (Blank {}).feed(input)?.0;
+        let token = self.get_token();
+        Ok((new_state , Some(token)))
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::Comment(self.value.clone())
+    }
+
+    fn get_type(&self) -> String {
+        String::from("CommentEnd")
+    }
+
+    fn get_value(&self) -> String {
+        self.value.clone()
+    }
+
+}
+
+struct Blank;
+
+// this state represnts the nothing state and can be used to transition into every other state
+impl State for Blank {
+
+    fn feed(&self , input: char) -> Result<Update , LexErrorKind> {
+        
+        if input.is_ascii_digit() {
+            let state = Box::new(Number::new(input.to_string())); 
+            return Ok((state , None));
+        }
+
+        if input.is_alphabetic() || input == '_' {
+            let state = Box::new(Ident::new(input.to_string() , false)); 
+            return Ok((state , None));
+        }
+
+        if input.is_whitespace() || input == '\n'  {
+            let state = Box::new(Blank {});
+            return Ok((state , None));
+        }
+
+        if is_operator(&input) {
+            let state = Box::new(Operator::new(String::from(input)));
+            return Ok((state , None));
+        }
+
+        if input == '"' {
+            let state = Box::new(Text::new(String::new()));
+            return Ok((state , None));
+        }
+
+        if input == '(' {
+            let state = Box::new(Lpar);
+            return Ok((state , None));
+        }
+
+        if input == ')' {
+            let state = Box::new(Rpar);
+            return Ok((state , None));
+        }
+
+        if input == '{' {
+            let state = Box::new(Lbr);
+            return Ok((state , None));
+        }
+
+        if input == '}' {
+            let state = Box::new(Rbr);
+            return Ok((state , None));
+        }
+
+        Err(LexErrorKind::UnexpectedCharacter)
+
+    }
+
+    fn get_token(&self) -> Token {
+        Token::Blank
+    }
+
+    fn get_type(&self) -> String {
+        String::from("Blank")
+    }
+
+}
+
+// this trait represnts the behaviour of a state machine and is used in the lexer
+// it starts with a Blank state then feeds input (1 character) to the current state
+// and then it transitions to the new state returned from the previous state
+// and returns a token if the previous state emitted a token
+
+// example:
+
+// '1' -> |Blank| -> |Number|
+// '2' -> |Number| -> |Number|
+// ' ' -> |Number| -> |Blank| and a Token::number(12) 
+
+// see https://en.wikipedia.org/wiki/Finite-state_machine for more detail
+
+// pos is the position of the input character in the source text
+// the machine uses it to give every token it emits a span
+
+pub trait Machine {
+    fn get_final_token(&self , end: Position) -> Result<SpannedToken , Errors>;
+    fn feed(&mut self , input: char , pos: Position) -> Result<Option<SpannedToken> , Errors>; 
+
+    // drops the token being built and goes back to the starting state
+    // returns the dropped text and where it started , if there was any
+    fn reset(&mut self) -> Option<(String , Position)>;
+}
+
+// an implementation of the Machine trait
+// start is where the token being processed by current_state began
+// comments are skipped unless emit_comments is set , tools that need them can keep them as tokens
+pub struct ImagineMachine {
+    current_state: Box<dyn State>,
+    start: Position,
+    emit_comments: bool,
+}
+
+impl Default for ImagineMachine {
+    fn default() -> ImagineMachine {
+        ImagineMachine::new()
+    }
+}
+
+impl ImagineMachine {
+
+    pub fn new() -> ImagineMachine {
+        ImagineMachine {current_state: Box::new(Blank {}) , start: Position::default() , emit_comments: false}
+    }
+
+    pub fn emit_comments(mut self , emit_comments: bool) -> ImagineMachine {
+        self.emit_comments = emit_comments;
+        self
+    }
+
+    fn is_hidden(&self , token: &Token) -> bool {
+        matches!(token , Token::Comment(_)) && !self.emit_comments
+    }
+
+}
+
+impl Machine for ImagineMachine {
+
+    fn get_final_token(&self , end: Position) -> Result<SpannedToken , Errors> {
+
+        // an unfinished token is reported where it started
+        let token = self.current_state.get_final_token().map_err(|kind| {
+            Errors::SyntaxError(LexError {
+                kind,
+                found: None,
+                state: self.current_state.get_type(),
+                position: self.start,
+            })
+        })?;
+
+        if self.is_hidden(&token) {
+            return Ok(SpannedToken { token: Token::Blank , span: Span::new(end , end) });
+        }
+
+        Ok(SpannedToken { token , span: Span::new(self.start , end) })
+
+    }
+
+    fn feed(&mut self , input: char , pos: Position) -> Result<Option<SpannedToken> , Errors> {
+
+        let (new_state , token) = self.current_state.feed(input).map_err(|kind| {
+            Errors::SyntaxError(LexError {
+                kind,
+                found: Some(input),
+                state: self.current_state.get_type(),
+                position: pos,
+            })
+        })?;
+
+        // the token ends right before the character that made the state emit it
+        let token = token.map(|token| SpannedToken { token , span: Span::new(self.start , pos) });
+
+        // a new token starts at this character if the machine was idle
+        // or the previous token just ended
+        if token.is_some() || self.current_state.get_type() == "Blank" {
+            self.start = pos;
+        }
+
+        self.current_state = new_state;
+        Ok(token.filter(|token| !self.is_hidden(&token.token)))
+        
+    }
+
+    fn reset(&mut self) -> Option<(String , Position)> {
+
+        let value = self.current_state.get_value();
+        self.current_state = Box::new(Blank {});
+
+        if value.is_empty() {
+            return None;
+        }
+
+        Some((value , self.start))
+
+    }
+
+}
@@ -0,0 +1,35 @@
+// lexes a few megabytes of source text with ImagineLexer and reports the throughput
+// run with cargo bench
+
+use std::time::Instant;
+
+use imagine_core::{
+    lexer::{ImagineLexer , Lexer},
+    statemachine::ImagineMachine,
+};
+
+const SNIPPET: &str = "player_speed += 12 * (delta - 3.25 ) if ready { emit \"dönë\\n\" } // ok\n";
+const TARGET_SIZE: usize = 4 * 1024 * 1024;
+const ROUNDS: usize = 5;
+
+fn main() {
+
+    let source = SNIPPET.repeat(TARGET_SIZE / SNIPPET.len());
+    let mut best = f64::MAX;
+    let mut tokens = 0;
+
+    for _ in 0..ROUNDS {
+
+        let mut lexer = ImagineLexer::new(source.clone() , ImagineMachine::new());
+        let now = Instant::now();
+
+        tokens = lexer.lex().unwrap().len();
+        best = best.min(now.elapsed().as_secs_f64());
+
+    }
+
+    let megabytes = source.len() as f64 / (1024.0 * 1024.0);
+
+    println!("lexer: {:.1} MiB , {} tokens in {:.3}s ({:.1} MiB/s)" , megabytes , tokens , best , megabytes / best);
+
+}
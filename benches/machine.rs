@@ -0,0 +1,73 @@
+// feeds a few megabytes of source text straight into an ImagineMachine and reports the throughput
+// the lexer is not used so only the cost of the state machine itself is measured
+// the same text is fed to the old machine with boxed states (see benches/boxed) to compare with
+// it doesn't know number suffixes or prefixes so the number literals are only fed to the new one
+// run with cargo bench
+
+mod boxed;
+
+use std::time::Instant;
+
+use imagine_core::{
+    common::Position,
+    statemachine::{ImagineMachine , Machine},
+};
+
+const SNIPPET: &str = "player_speed += 12 * (delta - 3.25 ) if ready { emit \"done\\n\" } // ok\n";
+const NUMBERS: &str = "limit = 4_294_967_295u64 + 0xdead_beef * 6.02e23f64\n";
+const TARGET_SIZE: usize = 4 * 1024 * 1024;
+const ROUNDS: usize = 5;
+
+// the best time of a few rounds of feeding the source to a new machine and the tokens it emitted
+// feed returns whether the machine emitted a token
+fn measure<M>(source: &str , new: impl Fn() -> M , feed: impl Fn(&mut M , char , Position) -> bool) -> (f64 , usize) {
+
+    let mut best = f64::MAX;
+    let mut tokens = 0;
+
+    for _ in 0..ROUNDS {
+
+        let mut machine = new();
+        let mut pos = Position::default();
+        let now = Instant::now();
+        tokens = 0;
+
+        for input in source.chars() {
+            if feed(&mut machine , input , pos) {
+                tokens += 1;
+            }
+            pos = pos.advance(input);
+        }
+
+        best = best.min(now.elapsed().as_secs_f64());
+
+    }
+
+    (best , tokens)
+
+}
+
+fn report(name: &str , source: &str , (seconds , tokens): (f64 , usize)) -> f64 {
+
+    let megabytes = source.len() as f64 / (1024.0 * 1024.0);
+    println!("{}: {:.1} MiB , {} tokens in {:.3}s ({:.1} MiB/s)" , name , megabytes , tokens , seconds , megabytes / seconds);
+
+    megabytes / seconds
+
+}
+
+fn main() {
+
+    let source = SNIPPET.repeat(TARGET_SIZE / SNIPPET.len());
+    let numbers = format!("{}{}" , SNIPPET , NUMBERS).repeat(TARGET_SIZE / (SNIPPET.len() + NUMBERS.len()));
+
+    let new = |machine: &mut ImagineMachine , input , pos| machine.feed(input , pos).unwrap().is_some();
+    let old = |machine: &mut boxed::ImagineMachine , input , pos| boxed::Machine::feed(machine , input , pos).unwrap().is_some();
+
+    let before = report("boxed states" , &source , measure(&source , boxed::ImagineMachine::new , old));
+    let after = report("machine" , &source , measure(&source , ImagineMachine::new , new));
+    report("machine with number literals" , &numbers , measure(&numbers , ImagineMachine::new , new));
+
+    println!("the machine is {:.1}x as fast as the boxed states" , after / before);
+
+}
@@ -0,0 +1,53 @@
+// runs the same script on the tree-walking interpreter and on the bytecode vm and reports both times
+// run with cargo bench
+
+use std::time::Instant;
+
+use imagine_core::{
+    interpreter::Interpreter,
+    vm::Vm,
+};
+
+const SCRIPT: &str = "
+    fn fib(n) {
+        if n < 2 { return n }
+        return fib(n - 1) + fib(n - 2)
+    }
+
+    let total = 0
+    let i = 0
+    while i < 100000 { total += i * 2 i += 1 }
+
+    fib(24) + total
+";
+
+const ROUNDS: usize = 5;
+
+// the best time of a few runs
+fn time(mut run: impl FnMut()) -> f64 {
+
+    let mut best = f64::MAX;
+
+    for _ in 0..ROUNDS {
+        let now = Instant::now();
+        run();
+        best = best.min(now.elapsed().as_secs_f64());
+    }
+
+    best
+
+}
+
+fn main() {
+
+    let interpreter = time(|| {
+        Interpreter::with_output(std::io::sink()).run_text(SCRIPT).unwrap();
+    });
+
+    let vm = time(|| {
+        Vm::with_output(std::io::sink()).run_text(SCRIPT).unwrap();
+    });
+
+    println!("interpreter: {:.3}s , vm: {:.3}s ({:.1}x faster)" , interpreter , vm , interpreter / vm);
+
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+
+// a non negative integer of any size , used for number literals that don't fit in 64 bits
+// limbs holds the value in base 2^32 with the least significant limb first
+// there are never any zero limbs at the end so every value has one representation
+#[derive(Debug , Clone , PartialEq , Eq)]
+pub struct BigInt {
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+
+    pub fn zero() -> BigInt {
+        BigInt { limbs: vec![] }
+    }
+
+    // parses digits in the given radix (2 to 36) , returns None if there are no digits
+    // or one of them is not valid in the radix
+    pub fn from_str_radix(digits: &str , radix: u32) -> Option<BigInt> {
+
+        if digits.is_empty() {
+            return None;
+        }
+
+        let mut value = BigInt::zero();
+
+        for input in digits.chars() {
+            let digit = input.to_digit(radix)?;
+            value.mul_add(radix , digit);
+        }
+
+        Some(value)
+
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    // the value if it fits in a u64
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.limbs.as_slice() {
+            [] => Some(0),
+            [low] => Some(*low as u64),
+            [low , high] => Some((*high as u64) << 32 | *low as u64),
+            _ => None,
+        }
+    }
+
+    // the value if it fits in an i64
+    pub fn to_i64(&self) -> Option<i64> {
+        self.to_u64().and_then(|value| i64::try_from(value).ok())
+    }
+
+    // the number of bits needed to write the value , 0 for zero
+    pub fn bits(&self) -> u64 {
+        match self.limbs.last() {
+            Some(last) => self.limbs.len() as u64 * 32 - last.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    // self = self * factor + add
+    fn mul_add(&mut self , factor: u32 , add: u32) {
+
+        let mut carry = add as u64;
+
+        for limb in self.limbs.iter_mut() {
+            let product = *limb as u64 * factor as u64 + carry;
+            *limb = product as u32;
+            carry = product >> 32;
+        }
+
+        if carry != 0 {
+            self.limbs.push(carry as u32);
+        }
+
+    }
+
+    // self = self / divisor , returns the remainder
+    fn div_rem(&mut self , divisor: u32) -> u32 {
+
+        let mut remainder = 0u64;
+
+        for limb in self.limbs.iter_mut().rev() {
+            let current = remainder << 32 | *limb as u64;
+            *limb = (current / divisor as u64) as u32;
+            remainder = current % divisor as u64;
+        }
+
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+
+        remainder as u32
+
+    }
+
+}
+
+impl From<u64> for BigInt {
+
+    fn from(value: u64) -> BigInt {
+
+        let mut limbs = vec![value as u32 , (value >> 32) as u32];
+
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+
+        BigInt { limbs }
+
+    }
+
+}
+
+impl fmt::Display for BigInt {
+
+    // writes the value in decimal , 9 digits at a time
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+
+        if self.is_zero() {
+            return write!(f , "0");
+        }
+
+        let mut value = self.clone();
+        let mut chunks = vec![];
+
+        while !value.is_zero() {
+            chunks.push(value.div_rem(1_000_000_000));
+        }
+
+        let mut chunks = chunks.iter().rev();
+
+        write!(f , "{}" , chunks.next().unwrap())?;
+
+        for chunk in chunks {
+            write!(f , "{:09}" , chunk)?;
+        }
+
+        Ok(())
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+
+        let digits = "123456789012345678901234567890";
+        assert_eq!(BigInt::from_str_radix(digits , 10).unwrap().to_string() , digits);
+
+        let value = BigInt::from_str_radix("ffffffffffffffffff" , 16).unwrap();
+        assert_eq!(value.to_string() , "4722366482869645213695");
+        assert_eq!(value.bits() , 72);
+
+        assert_eq!(BigInt::from_str_radix("0" , 10).unwrap().to_string() , "0");
+        assert_eq!(BigInt::from_str_radix("1000000000" , 10).unwrap().to_string() , "1000000000");
+
+        assert!(BigInt::from_str_radix("" , 10).is_none());
+        assert!(BigInt::from_str_radix("102" , 2).is_none());
+
+    }
+
+    #[test]
+    fn conversions() {
+
+        let max = BigInt::from(u64::MAX);
+
+        assert_eq!(max.to_string() , u64::MAX.to_string());
+        assert_eq!(max.to_u64() , Some(u64::MAX));
+        assert_eq!(max.to_i64() , None);
+        assert_eq!(BigInt::from(42).to_i64() , Some(42));
+        assert_eq!(BigInt::from(0) , BigInt::zero());
+
+    }
+
+}
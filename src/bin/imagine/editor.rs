@@ -0,0 +1,384 @@
+use std::{
+    io::{self , BufRead , IsTerminal , Read , Write},
+    process::{Command , Stdio},
+};
+
+// the keys the editor knows , anything else is Other and ignored
+#[derive(Debug , Clone , Copy , PartialEq , Eq)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    // ctrl-c
+    Interrupt,
+    // ctrl-d or the end of the input
+    Eof,
+    Other,
+}
+
+// what reading a line ended with
+#[derive(Debug , Clone , PartialEq , Eq)]
+pub enum Event {
+    Line(String),
+    Interrupt,
+    Eof,
+}
+
+fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+
+    let mut byte = [0];
+
+    match input.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+
+}
+
+// reads one key from the bytes a terminal sends in raw mode
+// arrows and the like come as escape sequences like ESC [ A and characters as utf-8
+pub fn read_key(input: &mut impl Read) -> io::Result<Key> {
+
+    let first = match read_byte(input)? {
+        Some(byte) => byte,
+        None => return Ok(Key::Eof),
+    };
+
+    let key = match first {
+        b'\r' | b'\n' => Key::Enter,
+        0x7f | 0x08 => Key::Backspace,
+        0x03 => Key::Interrupt,
+        0x04 => Key::Eof,
+        0x01 => Key::Home,
+        0x05 => Key::End,
+        0x1b => read_escape(input)?,
+        byte if byte < 0x20 => Key::Other,
+        byte => {
+
+            // the leading byte says how many bytes the character has
+            let length = match byte {
+                0x00..=0x7f => 1,
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => return Ok(Key::Other),
+            };
+
+            let mut bytes = vec![byte];
+
+            for _ in 1..length {
+                match read_byte(input)? {
+                    Some(byte) => bytes.push(byte),
+                    None => return Ok(Key::Eof),
+                }
+            }
+
+            match std::str::from_utf8(&bytes) {
+                Ok(text) => Key::Char(text.chars().next().unwrap()),
+                Err(_) => Key::Other,
+            }
+
+        }
+    };
+
+    Ok(key)
+
+}
+
+// the rest of an escape sequence , the ESC is already read
+fn read_escape(input: &mut impl Read) -> io::Result<Key> {
+
+    if !matches!(read_byte(input)? , Some(b'[') | Some(b'O')) {
+        return Ok(Key::Other);
+    }
+
+    let key = match read_byte(input)? {
+        Some(b'A') => Key::Up,
+        Some(b'B') => Key::Down,
+        Some(b'C') => Key::Right,
+        Some(b'D') => Key::Left,
+        Some(b'H') => Key::Home,
+        Some(b'F') => Key::End,
+        // ESC [ 3 ~ and friends
+        Some(digit @ b'0'..=b'9') => {
+
+            if read_byte(input)? != Some(b'~') {
+                return Ok(Key::Other);
+            }
+
+            match digit {
+                b'3' => Key::Delete,
+                b'1' | b'7' => Key::Home,
+                b'4' | b'8' => Key::End,
+                _ => Key::Other,
+            }
+
+        }
+        _ => Key::Other,
+    };
+
+    Ok(key)
+
+}
+
+// the line being edited
+// history_index is the entry of the history being shown , history.len() is the line being typed
+// and draft keeps that line while the user looks at older ones
+struct EditState {
+    line: Vec<char>,
+    cursor: usize,
+    history_index: usize,
+    draft: Vec<char>,
+}
+
+impl EditState {
+
+    fn new(history: &[String]) -> EditState {
+        EditState { line: vec![] , cursor: 0 , history_index: history.len() , draft: vec![] }
+    }
+
+    fn show_history(&mut self , history: &[String] , index: usize) {
+
+        if self.history_index == history.len() {
+            self.draft = self.line.clone();
+        }
+
+        self.history_index = index;
+        self.line = match history.get(index) {
+            Some(entry) => entry.chars().collect(),
+            None => self.draft.clone(),
+        };
+        self.cursor = self.line.len();
+
+    }
+
+    // changes the line for the key , returns the event once the line is done
+    fn apply(&mut self , key: Key , history: &[String]) -> Option<Event> {
+
+        match key {
+            Key::Char(input) => {
+                self.line.insert(self.cursor , input);
+                self.cursor += 1;
+            }
+            Key::Enter => return Some(Event::Line(self.line.iter().collect())),
+            Key::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.line.remove(self.cursor);
+            }
+            Key::Delete if self.cursor < self.line.len() => {
+                self.line.remove(self.cursor);
+            }
+            Key::Left => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right => self.cursor = (self.cursor + 1).min(self.line.len()),
+            Key::Home => self.cursor = 0,
+            Key::End => self.cursor = self.line.len(),
+            Key::Up if self.history_index > 0 => self.show_history(history , self.history_index - 1),
+            Key::Down if self.history_index < history.len() => self.show_history(history , self.history_index + 1),
+            Key::Interrupt => return Some(Event::Interrupt),
+            // ctrl-d only ends the input on an empty line like in a shell
+            Key::Eof if self.line.is_empty() => return Some(Event::Eof),
+            _ => {}
+        }
+
+        None
+
+    }
+
+}
+
+// puts the terminal in raw mode with stty and puts it back when dropped
+// saved is the old mode as printed by stty -g
+struct RawMode {
+    saved: String,
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other("stty failed"));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+
+}
+
+impl RawMode {
+
+    fn enable() -> io::Result<RawMode> {
+
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon" , "-echo" , "-isig" , "min" , "1"])?;
+
+        Ok(RawMode { saved })
+
+    }
+
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+// reads lines with editing and history when stdin is a terminal
+// and plain lines when it is a pipe or stty is missing
+// the terminal stays in raw mode from new until the editor is dropped , raw is None without one
+pub struct LineEditor {
+    history: Vec<String>,
+    raw: Option<RawMode>,
+}
+
+impl LineEditor {
+
+    pub fn new() -> LineEditor {
+
+        let raw = match io::stdin().is_terminal() {
+            true => RawMode::enable().ok(),
+            false => None,
+        };
+
+        LineEditor { history: vec![] , raw }
+
+    }
+
+    pub fn read_line(&mut self , prompt: &str) -> io::Result<Event> {
+
+        let event = match self.raw {
+            Some(_) => self.edit_line(prompt)?,
+            None => plain_line(prompt)?,
+        };
+
+        if let Event::Line(line) = &event {
+            if !line.trim().is_empty() && self.history.last() != Some(line) {
+                self.history.push(line.clone());
+            }
+        }
+
+        Ok(event)
+
+    }
+
+    fn edit_line(&self , prompt: &str) -> io::Result<Event> {
+
+        let mut input = io::stdin().lock();
+        let mut output = io::stdout().lock();
+        let mut state = EditState::new(&self.history);
+
+        loop {
+
+            // draws the whole line again and moves the cursor back to where it is
+            let line: String = state.line.iter().collect();
+            write!(output , "\r{}{}\x1b[K" , prompt , line)?;
+
+            if state.cursor < state.line.len() {
+                write!(output , "\x1b[{}D" , state.line.len() - state.cursor)?;
+            }
+
+            output.flush()?;
+
+            if let Some(event) = state.apply(read_key(&mut input)? , &self.history) {
+                write!(output , "\r\n")?;
+                return Ok(event);
+            }
+
+        }
+
+    }
+
+}
+
+fn plain_line(prompt: &str) -> io::Result<Event> {
+
+    print!("{}" , prompt);
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+
+    if io::stdin().lock().read_line(&mut line)? == 0 {
+        return Ok(Event::Eof);
+    }
+
+    Ok(Event::Line(line.trim_end_matches(['\n' , '\r']).to_string()))
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn keys(bytes: &[u8]) -> Vec<Key> {
+
+        let mut input = bytes;
+        let mut keys = vec![];
+
+        loop {
+            match read_key(&mut input).unwrap() {
+                Key::Eof => return keys,
+                key => keys.push(key),
+            }
+        }
+
+    }
+
+    #[test]
+    fn decode_keys() {
+
+        assert_eq!(keys("aé\x1b[A\x1b[D\x1b[3~\x7f\r\x03\x1bOH".as_bytes()) , vec![
+            Key::Char('a'),
+            Key::Char('é'),
+            Key::Up,
+            Key::Left,
+            Key::Delete,
+            Key::Backspace,
+            Key::Enter,
+            Key::Interrupt,
+            Key::Home,
+        ]);
+
+    }
+
+    #[test]
+    fn edit_and_history() {
+
+        let history = vec![String::from("first") , String::from("second")];
+        let mut state = EditState::new(&history);
+
+        for key in [Key::Char('a') , Key::Char('c') , Key::Left , Key::Char('b') , Key::End , Key::Char('!')] {
+            assert_eq!(state.apply(key , &history) , None);
+        }
+
+        assert_eq!(state.line.iter().collect::<String>() , "abc!");
+
+        // going back in the history and down again brings the draft back
+        state.apply(Key::Up , &history);
+        state.apply(Key::Up , &history);
+        state.apply(Key::Up , &history);
+        assert_eq!(state.line.iter().collect::<String>() , "first");
+
+        state.apply(Key::Down , &history);
+        state.apply(Key::Down , &history);
+        assert_eq!(state.line.iter().collect::<String>() , "abc!");
+
+        state.apply(Key::Home , &history);
+        state.apply(Key::Delete , &history);
+        state.apply(Key::Backspace , &history);
+
+        assert_eq!(state.apply(Key::Eof , &history) , None);
+        assert_eq!(state.apply(Key::Enter , &history) , Some(Event::Line(String::from("bc!"))));
+
+        assert_eq!(EditState::new(&history).apply(Key::Eof , &history) , Some(Event::Eof));
+
+    }
+
+}
@@ -0,0 +1,61 @@
+mod editor;
+mod repl;
+mod tokens;
+
+use std::io;
+
+use editor::{Event , LineEditor};
+use repl::{Repl , Step};
+
+// imagine with no arguments starts the repl
+// imagine tokens [options] [file] lists the tokens of a file (see tokens::main)
+// imagine graph prints the transitions of the lexer as a graphviz graph (see graphviz::transition_graph)
+fn main() {
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        None => repl(),
+        Some("tokens") => std::process::exit(tokens::main(&args[1..])),
+        Some("graph") => print!("{}" , imagine_core::graphviz::transition_graph()),
+        Some(command) => {
+            eprintln!("imagine: unknown command {}\n\nusage: imagine [tokens [options] [file] | graph]" , command);
+            std::process::exit(2);
+        }
+    }
+
+}
+
+fn repl() {
+
+    println!("imagine {} , :help for the commands" , env!("CARGO_PKG_VERSION"));
+
+    let mut editor = LineEditor::new();
+    let mut repl = Repl::new(io::stdout());
+
+    loop {
+
+        let step = match editor.read_line(repl.prompt()) {
+            Ok(Event::Line(line)) => repl.entry(&line),
+            Ok(Event::Interrupt) => {
+                repl.cancel();
+                Ok(Step::Done)
+            }
+            Ok(Event::Eof) => break,
+            Err(error) => Err(error),
+        };
+
+        match step {
+            Ok(Step::Quit) => break,
+            Ok(_) => {}
+            Err(error) => {
+                // exit skips the drops so the terminal is put back first
+                drop(editor);
+                eprintln!("imagine: {}" , error);
+                std::process::exit(1);
+            }
+        }
+
+    }
+
+}
@@ -0,0 +1,309 @@
+use std::{
+    cell::RefCell,
+    io::{self , Write},
+    rc::Rc,
+};
+
+use imagine_core::{
+    common::{Errors , LexErrorKind},
+    compiler::compile,
+    interpreter::{Interpreter , Value},
+    lexer::{ImagineLexer , Lexer},
+    parser::Parser,
+    statemachine::{ImagineMachine , Token , Transition},
+};
+
+// what the repl wants after an entry
+#[derive(Debug , Clone , Copy , PartialEq , Eq)]
+pub enum Step {
+    // the entry is not complete yet , like an open { or (
+    More,
+    Done,
+    Quit,
+}
+
+const HELP: &str = "\
+:tokens <code>  shows the tokens of the code
+:trace <code>   shows every step the lexer's state machine takes on the code
+:dis <code>     shows the bytecode of the code
+:help           shows this
+:quit           leaves (so does ctrl-d)
+an open ( , { , [ , string or comment continues the entry on the next line
+ctrl-c drops the entry";
+
+// evaluates entries one after another , variables and functions are kept between them
+// pending holds the lines of an entry that is not complete yet
+pub struct Repl<W: Write> {
+    interpreter: Interpreter<W>,
+    pending: String,
+}
+
+impl<W: Write> Repl<W> {
+
+    pub fn new(output: W) -> Repl<W> {
+        Repl { interpreter: Interpreter::with_output(output) , pending: String::new() }
+    }
+
+    pub fn prompt(&self) -> &'static str {
+        if self.pending.is_empty() { "> " } else { ".. " }
+    }
+
+    #[cfg(test)]
+    pub fn output(&self) -> &W {
+        self.interpreter.output()
+    }
+
+    // drops the lines of an entry that is not complete
+    pub fn cancel(&mut self) {
+        self.pending.clear();
+    }
+
+    pub fn entry(&mut self , line: &str) -> io::Result<Step> {
+
+        if self.pending.is_empty() && line.trim_start().starts_with(':') {
+            return self.command(line.trim());
+        }
+
+        self.pending.push_str(line);
+        self.pending.push('\n');
+
+        if needs_more(&self.pending) {
+            return Ok(Step::More);
+        }
+
+        let source = std::mem::take(&mut self.pending);
+
+        if source.trim().is_empty() {
+            return Ok(Step::Done);
+        }
+
+        let result = self.interpreter.run_text(&source);
+        let output = self.interpreter.output_mut();
+
+        match result {
+            Ok(Value::Nil) => {}
+            Ok(Value::Str(text)) => writeln!(output , "{:?}" , text)?,
+            Ok(value) => writeln!(output , "{}" , value)?,
+            Err(error) => write!(output , "{}" , report(&source , &error))?,
+        }
+
+        Ok(Step::Done)
+
+    }
+
+    fn command(&mut self , line: &str) -> io::Result<Step> {
+
+        let (name , code) = line.split_once(' ').unwrap_or((line , ""));
+        let output = self.interpreter.output_mut();
+
+        match name {
+            ":quit" | ":q" => return Ok(Step::Quit),
+            ":help" => writeln!(output , "{}" , HELP)?,
+            ":tokens" => write!(output , "{}" , tokens(code))?,
+            ":trace" => write!(output , "{}" , trace(code))?,
+            ":dis" => write!(output , "{}" , disassemble(code))?,
+            _ => writeln!(output , "unknown command {} , try :help" , name)?,
+        }
+
+        Ok(Step::Done)
+
+    }
+
+}
+
+// whether the entry stops in the middle of something , then the next line belongs to it
+pub fn needs_more(source: &str) -> bool {
+
+    let tokens = match ImagineLexer::new(String::from(source) , ImagineMachine::new()).lex() {
+        Ok(tokens) => tokens,
+        Err(Errors::SyntaxError(error)) => {
+            return error.found.is_none() && matches!(error.kind , LexErrorKind::UnterminatedLiteral | LexErrorKind::UnterminatedComment);
+        }
+        Err(_) => return false,
+    };
+
+    let mut depth = 0;
+
+    for token in tokens {
+        match token.token {
+            Token::LPAR | Token::LBR | Token::LSQB => depth += 1,
+            Token::RPAR | Token::RBR | Token::RSQB => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth > 0
+
+}
+
+// one token a line with where it is in the code
+pub fn tokens(code: &str) -> String {
+
+    let tokens = match ImagineLexer::new(String::from(code) , ImagineMachine::new()).lex() {
+        Ok(tokens) => tokens,
+        Err(error) => return report(code , &error),
+    };
+
+    let mut text = String::new();
+
+    for token in tokens {
+        let span = format!("{}-{}" , token.span.start , token.span.end);
+        text.push_str(&format!("{:<12} {:?}\n" , span , token.token));
+    }
+
+    text
+
+}
+
+// one transition of the state machine a line , the errors are listed where they happen
+pub fn trace(code: &str) -> String {
+
+    let steps = Rc::new(RefCell::new(String::new()));
+    let recorder = steps.clone();
+
+    let machine = ImagineMachine::new().trace(move |step: &Transition| {
+        recorder.borrow_mut().push_str(&format!("{}\n" , step));
+    });
+
+    ImagineLexer::new(String::from(code) , machine).lex_recovering();
+
+    steps.take()
+
+}
+
+fn disassemble(code: &str) -> String {
+
+    let compiled = ImagineLexer::new(String::from(code) , ImagineMachine::new())
+        .lex()
+        .and_then(|tokens| Parser::new(tokens).parse_program())
+        .and_then(|program| compile(&program));
+
+    match compiled {
+        Ok(function) => function.disassemble(),
+        Err(error) => report(code , &error),
+    }
+
+}
+
+// the error with the line it happened on and a mark under the part that is wrong
+//
+//   error message
+//      2 | let x = 1 +
+//        |           ^
+pub fn report(source: &str , error: &Errors) -> String {
+
+    let mut text = format!("{}\n" , error);
+
+    let span = match error.span() {
+        Some(span) => span,
+        None => return text,
+    };
+
+    let line = match source.lines().nth(span.start.line - 1) {
+        Some(line) => line,
+        None => return text,
+    };
+
+    // the mark covers the span if it ends on the same line
+    let width = match span.end.line == span.start.line {
+        true => span.end.column.saturating_sub(span.start.column).max(1),
+        false => line.chars().count().saturating_sub(span.start.column - 1).max(1),
+    };
+
+    let number = span.start.line.to_string();
+    let gutter = " ".repeat(number.len());
+
+    text.push_str(&format!("{} | {}\n" , number , line));
+    text.push_str(&format!("{} | {}{}\n" , gutter , " ".repeat(span.start.column - 1) , "^".repeat(width)));
+
+    text
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // feeds the lines to a new repl and returns what it wrote
+    fn session(lines: &[&str]) -> (String , Vec<Step>) {
+
+        let mut repl = Repl::new(vec![]);
+        let steps = lines.iter().map(|line| repl.entry(line).unwrap()).collect();
+
+        (String::from_utf8(repl.output().clone()).unwrap() , steps)
+
+    }
+
+    #[test]
+    fn multi_line_entries() {
+
+        assert!(needs_more("fn f(a) {\n"));
+        assert!(needs_more("print(1 ,\n"));
+        assert!(needs_more("let s = \"open\n"));
+        assert!(needs_more("/* still\n"));
+        assert!(!needs_more("fn f(a) { return a }\n"));
+        assert!(!needs_more("1 +\n"));
+        assert!(!needs_more("x @ y\n"));
+
+        let (output , steps) = session(&[
+            "fn add(a , b) {",
+            "  return a + b",
+            "}",
+            "add(1 , 2)",
+            "\"text\"",
+            "print(\"hi\")",
+            ":quit",
+        ]);
+
+        assert_eq!(steps , vec![Step::More , Step::More , Step::Done , Step::Done , Step::Done , Step::Done , Step::Quit]);
+        assert_eq!(output , "3\n\"text\"\nhi\n");
+
+    }
+
+    #[test]
+    fn errors_are_positioned() {
+
+        let (output , _) = session(&["let x = 1" , "x + \"a\""]);
+
+        assert_eq!(output , [
+            "runtime error at 1:1: can't apply + to int and string",
+            "1 | x + \"a\"",
+            "  | ^^^^^^^",
+            "",
+        ].join("\n"));
+
+        let (output , _) = session(&["if x {" , "  1 2 +" , "}"]);
+
+        assert_eq!(output , [
+            "parse error at 3:1: expected an expression , found }",
+            "3 | }",
+            "  | ^",
+            "",
+        ].join("\n"));
+
+    }
+
+    #[test]
+    fn commands() {
+
+        let (output , _) = session(&[":tokens let x = 1" , ":nope"]);
+
+        assert_eq!(output , [
+            "1:1-1:4      Keyword(Let)",
+            "1:5-1:6      Ident(\"x\")",
+            "1:7-1:8      Operator(Assign)",
+            "1:9-1:10     Number(1)",
+            "unknown command :nope , try :help",
+            "",
+        ].join("\n"));
+
+        let (output , _) = session(&[":trace a+"]);
+        assert_eq!(output , "Blank --'a'--> Ident\nIdent --'+'--> Operator emits Ident(\"a\")\n");
+
+        let (output , _) = session(&[":dis 1 + 2"]);
+        assert!(output.starts_with("== <script> ==\n0000     1:1  Constant"));
+
+    }
+
+}
@@ -0,0 +1,254 @@
+use std::{
+    fs,
+    io::{self , Read , Write},
+};
+
+use imagine_core::{
+    common::Position,
+    lexer::{ImagineLexer , Lexer},
+    statemachine::{ImagineMachine , SpannedToken},
+};
+
+use crate::repl::report;
+
+const USAGE: &str = "\
+usage: imagine tokens [options] [file]
+
+lexes the file , or stdin if there is no file or it is -
+and prints one token a line
+
+options:
+  --json          prints the tokens as json lines
+  --comments      keeps the comments
+  --big-integers  lexes integers too big for an i64 as BigInt";
+
+// the options of the subcommand , path is None for stdin
+#[derive(Debug , Default , PartialEq)]
+struct Options {
+    json: bool,
+    comments: bool,
+    big_integers: bool,
+    path: Option<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Options , String> {
+
+    let mut options = Options::default();
+
+    for arg in args {
+        match arg.as_str() {
+            "--json" => options.json = true,
+            "--comments" => options.comments = true,
+            "--big-integers" => options.big_integers = true,
+            "-" if options.path.is_none() => {}
+            flag if flag.starts_with('-') => return Err(format!("unknown option {}" , flag)),
+            path if options.path.is_none() => options.path = Some(String::from(path)),
+            path => return Err(format!("unexpected argument {}" , path)),
+        }
+    }
+
+    Ok(options)
+
+}
+
+// runs the subcommand and returns the exit code
+// the tokens go to stdout and the errors to stderr , the text the lexer could not make sense of
+// is still listed as Error tokens so the stream covers the whole input
+pub fn main(args: &[String]) -> i32 {
+
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}" , USAGE);
+        return 0;
+    }
+
+    let options = match parse_args(args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("imagine tokens: {}\n\n{}" , message , USAGE);
+            return 2;
+        }
+    };
+
+    let source = match &options.path {
+        Some(path) => fs::read_to_string(path),
+        None => {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source).map(|_| source)
+        }
+    };
+
+    let source = match source {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("imagine tokens: {}" , error);
+            return 1;
+        }
+    };
+
+    let machine = ImagineMachine::new().emit_comments(options.comments).big_integers(options.big_integers);
+    let (tokens , errors) = ImagineLexer::new(source.clone() , machine).lex_recovering();
+
+    let mut output = io::stdout().lock();
+    let written = match options.json {
+        true => write_json(&mut output , &source , &tokens),
+        false => write_table(&mut output , &source , &tokens),
+    };
+
+    if let Err(error) = written.and_then(|_| output.flush()) {
+        // a closed pipe like imagine tokens | head is not worth a message
+        if error.kind() != io::ErrorKind::BrokenPipe {
+            eprintln!("imagine tokens: {}" , error);
+        }
+        return 1;
+    }
+
+    for error in &errors {
+        eprint!("{}" , report(&source , error));
+    }
+
+    if errors.is_empty() { 0 } else { 1 }
+
+}
+
+// the part of the source text the token came from
+fn text_of<'a>(source: &'a str , token: &SpannedToken) -> &'a str {
+    &source[token.span.start.offset..token.span.end.offset]
+}
+
+// KIND  TEXT  LINE:COL with the text escaped so a token stays on its line
+fn write_table(output: &mut impl Write , source: &str , tokens: &[SpannedToken]) -> io::Result<()> {
+
+    let rows: Vec<(&str , String , String)> = tokens
+        .iter()
+        .map(|token| (
+            token.token.get_type(),
+            text_of(source , token).escape_debug().to_string(),
+            token.span.start.to_string(),
+        ))
+        .collect();
+
+    let kind_width = rows.iter().map(|row| row.0.len()).chain([4]).max().unwrap();
+    let text_width = rows.iter().map(|row| row.1.chars().count()).chain([4]).max().unwrap();
+
+    writeln!(output , "{:<kind_width$}  {:<text_width$}  LINE:COL" , "KIND" , "TEXT")?;
+
+    for (kind , text , position) in rows {
+        writeln!(output , "{:<kind_width$}  {:<text_width$}  {}" , kind , text , position)?;
+    }
+
+    Ok(())
+
+}
+
+// one json object a line , the end of a span is where the next character would be
+// {"kind":"Ident","text":"x","start":{"line":1,"column":5,"offset":4},"end":{...}}
+fn write_json(output: &mut impl Write , source: &str , tokens: &[SpannedToken]) -> io::Result<()> {
+
+    for token in tokens {
+        writeln!(
+            output,
+            "{{\"kind\":\"{}\",\"text\":{},\"start\":{},\"end\":{}}}",
+            token.token.get_type(),
+            json_string(text_of(source , token)),
+            json_position(token.span.start),
+            json_position(token.span.end),
+        )?;
+    }
+
+    Ok(())
+
+}
+
+fn json_position(position: Position) -> String {
+    format!("{{\"line\":{},\"column\":{},\"offset\":{}}}" , position.line , position.column , position.offset)
+}
+
+fn json_string(text: &str) -> String {
+
+    let mut json = String::from("\"");
+
+    for input in text.chars() {
+        match input {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            input if (input as u32) < 0x20 => json.push_str(&format!("\\u{:04x}" , input as u32)),
+            input => json.push(input),
+        }
+    }
+
+    json.push('"');
+    json
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn lexed(source: &str) -> Vec<SpannedToken> {
+        ImagineLexer::new(String::from(source) , ImagineMachine::new().emit_comments(true)).lex_recovering().0
+    }
+
+    fn written(json: bool , source: &str) -> String {
+
+        let mut output = vec![];
+        let tokens = lexed(source);
+
+        match json {
+            true => write_json(&mut output , source , &tokens).unwrap(),
+            false => write_table(&mut output , source , &tokens).unwrap(),
+        }
+
+        String::from_utf8(output).unwrap()
+
+    }
+
+    #[test]
+    fn table() {
+
+        assert_eq!(written(false , "let x = \"a\\tb\"\n// done") , [
+            "KIND      TEXT       LINE:COL",
+            "Keyword   let        1:1",
+            "Ident     x          1:5",
+            "Operator  =          1:7",
+            "Text      \\\"a\\\\tb\\\"  1:9",
+            "Comment   // done    2:1",
+            "",
+        ].join("\n"));
+
+    }
+
+    #[test]
+    fn json_lines() {
+
+        assert_eq!(written(true , "f(\"\\\"\")\n€ 1") , [
+            "{\"kind\":\"Ident\",\"text\":\"f\",\"start\":{\"line\":1,\"column\":1,\"offset\":0},\"end\":{\"line\":1,\"column\":2,\"offset\":1}}",
+            "{\"kind\":\"LPAR\",\"text\":\"(\",\"start\":{\"line\":1,\"column\":2,\"offset\":1},\"end\":{\"line\":1,\"column\":3,\"offset\":2}}",
+            "{\"kind\":\"Text\",\"text\":\"\\\"\\\\\\\"\\\"\",\"start\":{\"line\":1,\"column\":3,\"offset\":2},\"end\":{\"line\":1,\"column\":7,\"offset\":6}}",
+            "{\"kind\":\"RPAR\",\"text\":\")\",\"start\":{\"line\":1,\"column\":7,\"offset\":6},\"end\":{\"line\":1,\"column\":8,\"offset\":7}}",
+            "{\"kind\":\"Error\",\"text\":\"€\",\"start\":{\"line\":2,\"column\":1,\"offset\":8},\"end\":{\"line\":2,\"column\":2,\"offset\":11}}",
+            "{\"kind\":\"Number\",\"text\":\"1\",\"start\":{\"line\":2,\"column\":3,\"offset\":12},\"end\":{\"line\":2,\"column\":4,\"offset\":13}}",
+            "",
+        ].join("\n"));
+
+        assert_eq!(json_string("\u{1}\r") , "\"\\u0001\\r\"");
+
+    }
+
+    #[test]
+    fn arguments() {
+
+        let args = |args: &[&str]| parse_args(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>());
+
+        assert_eq!(args(&["--json" , "a.im"]).unwrap() , Options { json: true , path: Some(String::from("a.im")) , ..Options::default() });
+        assert_eq!(args(&["-" , "--comments"]).unwrap() , Options { comments: true , ..Options::default() });
+        assert!(args(&["--jsn"]).is_err());
+        assert!(args(&["a" , "b"]).is_err());
+
+    }
+
+}
@@ -0,0 +1,200 @@
+use std::{
+    cell::RefCell,
+    fmt::{self , Write},
+    rc::Rc,
+};
+
+use crate::{
+    statemachine::Op,
+    interpreter::Value,
+    common::Span,
+};
+
+// the instructions of the vm , every operand fits in 16 bits so an instruction is 4 bytes
+// the vm works on a stack of values , instructions pop their operands and push their result
+
+// Constant      pushes a value of the constant pool
+// Nil True False  push the value
+// Pop           drops the top value
+// GetLocal SetLocal  read or write a slot of the current call frame , Set leaves the value on the stack
+// GetGlobal SetGlobal DefineGlobal  the operand is the constant holding the name of the global
+// GetUpvalue SetUpvalue  read or write a variable the running closure captured , Set leaves the value on the stack
+// Closure       pushes a closure of the function constant with the variables it captures (see CompiledFunction::upvalues)
+// Unary Binary  apply an operator to the top value or the top two values
+// ToBool        replaces the top value with whether it is truthy
+// Jump          moves to the instruction at the operand
+// JumpIfFalse   pops the top value and jumps if it is not truthy
+// Call          calls the value below the arguments , the operand is the number of arguments
+// Return        ends the call frame and pushes the top value for the caller
+#[derive(Debug , Clone , Copy , PartialEq , Eq)]
+pub enum Instruction {
+    Constant(u16),
+    Nil,
+    True,
+    False,
+    Pop,
+    GetLocal(u16),
+    SetLocal(u16),
+    GetGlobal(u16),
+    SetGlobal(u16),
+    DefineGlobal(u16),
+    GetUpvalue(u16),
+    SetUpvalue(u16),
+    Closure(u16),
+    Unary(Op),
+    Binary(Op),
+    ToBool,
+    Jump(u16),
+    JumpIfFalse(u16),
+    Call(u8),
+    Return,
+}
+
+// the compiled code of one function
+// spans has the part of the source text every instruction came from , for runtime errors
+// constants holds the literals , the names of globals and the functions declared inside
+#[derive(Debug , Default)]
+pub struct Chunk {
+    pub code: Vec<Instruction>,
+    pub spans: Vec<Span>,
+    pub constants: Vec<Value>,
+}
+
+// where a closure gets a captured variable from when it is made
+// a slot of the function that makes it or one of that function's own upvalues
+#[derive(Debug , Clone , Copy , PartialEq , Eq)]
+pub enum Capture {
+    Local(u16),
+    Upvalue(u16),
+}
+
+// a function ready to run on the vm , the top level of a program is one too
+// upvalues lists the variables of the enclosing functions it uses , a function without any
+// is pushed as it is and one with some is made into a Closure when its declaration runs
+#[derive(Debug)]
+pub struct CompiledFunction {
+    pub name: String,
+    pub arity: usize,
+    pub upvalues: Vec<Capture>,
+    pub chunk: Chunk,
+}
+
+// a captured variable , it points at its slot on the vm's stack while the slot is in scope
+// and takes the value with it when the slot is popped so the closure can outlive the scope
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+// a function with the variables it captured , closures that capture each other are never freed
+pub struct Closure {
+    pub function: Rc<CompiledFunction>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+}
+
+impl fmt::Debug for Closure {
+
+    // the upvalues may hold the closure itself so they are left out
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f , "<fn {}>" , self.function.name)
+    }
+
+}
+
+impl CompiledFunction {
+
+    // lists the instructions of the function and then of every function declared in it
+    pub fn disassemble(&self) -> String {
+
+        let mut text = String::new();
+        self.disassemble_into(&mut text);
+        text
+
+    }
+
+    fn disassemble_into(&self , text: &mut String) {
+
+        let name = if self.name.is_empty() { "<script>" } else { self.name.as_str() };
+        writeln!(text , "== {} ==" , name).unwrap();
+
+        let chunk = &self.chunk;
+
+        for (index , instruction) in chunk.code.iter().enumerate() {
+
+            // the line is only written when it changes like in a listing
+            let position = chunk.spans[index].start;
+            let line = match index {
+                0 => position.to_string(),
+                _ if chunk.spans[index - 1].start.line == position.line => String::from("|"),
+                _ => position.to_string(),
+            };
+
+            writeln!(text , "{:04} {:>7}  {}" , index , line , instruction_text(instruction , chunk)).unwrap();
+
+        }
+
+        for constant in &chunk.constants {
+            if let Value::Compiled(function) = constant {
+                writeln!(text).unwrap();
+                function.disassemble_into(text);
+            }
+        }
+
+    }
+
+}
+
+// the name of the instruction and its operand , with the constant it refers to if there is one
+fn instruction_text(instruction: &Instruction , chunk: &Chunk) -> String {
+
+    let (name , operand) = match *instruction {
+        Instruction::Constant(constant) => return constant_text("Constant" , constant , chunk),
+        Instruction::GetGlobal(constant) => return constant_text("GetGlobal" , constant , chunk),
+        Instruction::SetGlobal(constant) => return constant_text("SetGlobal" , constant , chunk),
+        Instruction::DefineGlobal(constant) => return constant_text("DefineGlobal" , constant , chunk),
+        Instruction::Closure(constant) => return constant_text("Closure" , constant , chunk),
+        Instruction::GetLocal(slot) => ("GetLocal" , slot.to_string()),
+        Instruction::SetLocal(slot) => ("SetLocal" , slot.to_string()),
+        Instruction::GetUpvalue(index) => ("GetUpvalue" , index.to_string()),
+        Instruction::SetUpvalue(index) => ("SetUpvalue" , index.to_string()),
+        Instruction::Unary(op) => ("Unary" , op.to_string()),
+        Instruction::Binary(op) => ("Binary" , op.to_string()),
+        Instruction::Jump(target) => ("Jump" , target.to_string()),
+        Instruction::JumpIfFalse(target) => ("JumpIfFalse" , target.to_string()),
+        Instruction::Call(count) => ("Call" , count.to_string()),
+        Instruction::Nil => return String::from("Nil"),
+        Instruction::True => return String::from("True"),
+        Instruction::False => return String::from("False"),
+        Instruction::Pop => return String::from("Pop"),
+        Instruction::ToBool => return String::from("ToBool"),
+        Instruction::Return => return String::from("Return"),
+    };
+
+    format!("{:<14} {:>4}" , name , operand)
+
+}
+
+// strings are quoted in the listing so they can't be mistaken for names
+fn constant_text(name: &str , constant: u16 , chunk: &Chunk) -> String {
+
+    let value = match &chunk.constants[constant as usize] {
+        Value::Str(text) => format!("{:?}" , text),
+        value => value.to_string(),
+    };
+
+    format!("{:<14} {:>4} ; {}" , name , constant , value)
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn instruction_size() {
+        assert_eq!(std::mem::size_of::<Instruction>() , 4);
+    }
+
+}
@@ -1,4 +1,491 @@
-#[derive(Debug)]
+use std::fmt;
+
+use crate::statemachine::{Op , Token};
+
+// every error the crate can report
+#[derive(Debug , Clone , PartialEq)]
+// IoError is used when the source text can't be read (see source::Source)
+// ParseError is used when the tokens don't make a valid program (see parser::Parser)
+// RuntimeError is used when a valid program goes wrong while it runs (see interpreter::Interpreter)
+// CompileError is used when a valid program can't be turned into bytecode (see compiler::compile)
 pub enum Errors {
-    SyntaxError,
-}
\ No newline at end of file
+    SyntaxError(LexError),
+    IoError(String),
+    ParseError(ParseError),
+    RuntimeError(RuntimeError),
+    CompileError(CompileError),
+}
+
+// the different mistakes the lexer can find in the source text
+#[derive(Debug , Clone , PartialEq , Eq)]
+pub enum LexErrorKind {
+    UnexpectedCharacter,
+    MalformedNumber(NumberError),
+    UnterminatedLiteral,
+    IntegerOverflow,
+    FloatOverflow,
+    InvalidEscape,
+    UnterminatedComment,
+    // the input ended in the middle of a token of a dfa::DfaMachine
+    UnexpectedEnd,
+}
+
+// the ways a number literal can be malformed
+// MissingDigits       0x or 0b_ with no digits after the prefix
+// InvalidDigit        a digit too big for the radix like 0b102 or 0o78
+// MissingExponent     1e or 1e+ with no digits in the exponent
+// MisplacedSeparator  a _ that doesn't follow a digit like 1_ , 1_.5 or 1._5
+// UnexpectedDecimalPoint  a second decimal point like 1.2.3 , 1e5.3 or one in 0x1.5
+// InvalidSuffix       a suffix that isn't one of i8 to i64 , u8 to u64 , f32 or f64
+// IntegerSuffixOnFloat    1.5i32 or 1e3u8
+// FloatSuffixOnRadix  0b1f32
+#[derive(Debug , Clone , Copy , PartialEq , Eq)]
+pub enum NumberError {
+    MissingDigits,
+    InvalidDigit,
+    MissingExponent,
+    MisplacedSeparator,
+    UnexpectedDecimalPoint,
+    InvalidSuffix,
+    IntegerSuffixOnFloat,
+    FloatSuffixOnRadix,
+}
+
+// a lexical error and everything needed to report it
+// found is the offending character or None if the input ended too early
+// state is the name of the state that rejected it (see State::get_type)
+#[derive(Debug , Clone , PartialEq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub found: Option<char>,
+    pub state: String,
+    pub position: Position,
+}
+
+// the different mistakes the parser can find in the tokens
+// Expected is a token that has to come next like the ) closing a group
+// UnexpectedToken is a token left over after a complete expression
+// ExpectedName is a missing name like the one after let
+// TooDeep is code nested deeper than parser::MAX_DEPTH
+#[derive(Debug , Clone , PartialEq)]
+pub enum ParseErrorKind {
+    ExpectedExpression,
+    ExpectedName,
+    Expected(Token),
+    UnexpectedToken,
+    InvalidAssignmentTarget,
+    TooDeep,
+}
+
+// a parse error and everything needed to report it
+// found is the offending token or None if the tokens ended too early
+// span is where the offending token (or expression) is in the source text
+#[derive(Debug , Clone , PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub found: Option<Token>,
+    pub span: Span,
+}
+
+// the different ways a program can go wrong while it runs
+// the &'static str fields are type names like int or string (see interpreter::Value::type_name)
+#[derive(Debug , Clone , PartialEq)]
+pub enum RuntimeErrorKind {
+    UndefinedVariable(String),
+    InvalidOperand { op: Op , operand: &'static str },
+    InvalidOperands { op: Op , left: &'static str , right: &'static str },
+    DivisionByZero,
+    IntegerOverflow,
+    NotCallable(&'static str),
+    WrongArgumentCount { expected: usize , found: usize },
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    ReturnOutsideFunction,
+    StackOverflow,
+}
+
+// a runtime error and the part of the source text that caused it
+#[derive(Debug , Clone , PartialEq)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub span: Span,
+}
+
+// the limits of the bytecode and what the compiler can't do yet
+// IntegerOverflow is a big integer literal , the vm only has 64 bit ints
+#[derive(Debug , Clone , PartialEq)]
+pub enum CompileErrorKind {
+    IntegerOverflow,
+    TooManyConstants,
+    TooManyLocals,
+    TooManyArguments,
+    FunctionTooLong,
+    TooManyUpvalues,
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    ReturnOutsideFunction,
+}
+
+// a compile error and the part of the source text that caused it
+#[derive(Debug , Clone , PartialEq)]
+pub struct CompileError {
+    pub kind: CompileErrorKind,
+    pub span: Span,
+}
+
+impl fmt::Display for LexErrorKind {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+
+        let message = match self {
+            LexErrorKind::UnexpectedCharacter => "unexpected character",
+            LexErrorKind::MalformedNumber(error) => return write!(f , "malformed number: {}" , error),
+            LexErrorKind::UnterminatedLiteral => "unterminated literal",
+            LexErrorKind::IntegerOverflow => "integer literal is too large",
+            LexErrorKind::FloatOverflow => "float literal is too large",
+            LexErrorKind::InvalidEscape => "invalid escape sequence",
+            LexErrorKind::UnterminatedComment => "unterminated block comment",
+            LexErrorKind::UnexpectedEnd => "the input ended in the middle of a token",
+        };
+
+        write!(f , "{}" , message)
+
+    }
+
+}
+
+impl fmt::Display for NumberError {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+
+        let message = match self {
+            NumberError::MissingDigits => "no digits after the radix prefix",
+            NumberError::InvalidDigit => "digit is too big for the radix",
+            NumberError::MissingExponent => "no digits in the exponent",
+            NumberError::MisplacedSeparator => "_ has to come after a digit",
+            NumberError::UnexpectedDecimalPoint => "unexpected decimal point",
+            NumberError::InvalidSuffix => "invalid type suffix",
+            NumberError::IntegerSuffixOnFloat => "integer suffix on a float literal",
+            NumberError::FloatSuffixOnRadix => "float suffix on a hex , octal or binary literal",
+        };
+
+        write!(f , "{}" , message)
+
+    }
+
+}
+
+impl fmt::Display for LexError {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+
+        write!(f , "{}: {}" , self.position , self.kind)?;
+
+        match self.found {
+            Some(found) => write!(f , " {:?} in {} state" , found , self.state),
+            None => write!(f , " at the end of input in {} state" , self.state),
+        }
+
+    }
+
+}
+
+impl fmt::Display for ParseErrorKind {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseErrorKind::ExpectedExpression => write!(f , "expected an expression"),
+            ParseErrorKind::ExpectedName => write!(f , "expected a name"),
+            ParseErrorKind::Expected(token) => write!(f , "expected {}" , token),
+            ParseErrorKind::UnexpectedToken => write!(f , "unexpected token"),
+            ParseErrorKind::InvalidAssignmentTarget => write!(f , "only a name can be assigned to"),
+            ParseErrorKind::TooDeep => write!(f , "code is nested too deep"),
+        }
+    }
+
+}
+
+impl fmt::Display for ParseError {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+
+        write!(f , "{}: {}" , self.span.start , self.kind)?;
+
+        match &self.found {
+            Some(found) => write!(f , " , found {}" , found),
+            None => write!(f , " at the end of input"),
+        }
+
+    }
+
+}
+
+impl fmt::Display for RuntimeErrorKind {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeErrorKind::UndefinedVariable(name) => write!(f , "undefined variable {}" , name),
+            RuntimeErrorKind::InvalidOperand { op , operand } => write!(f , "can't apply {} to {}" , op , operand),
+            RuntimeErrorKind::InvalidOperands { op , left , right } => {
+                write!(f , "can't apply {} to {} and {}" , op , left , right)
+            }
+            RuntimeErrorKind::DivisionByZero => write!(f , "division by zero"),
+            RuntimeErrorKind::IntegerOverflow => write!(f , "integer overflow"),
+            RuntimeErrorKind::NotCallable(found) => write!(f , "{} is not a function" , found),
+            RuntimeErrorKind::WrongArgumentCount { expected , found } => {
+                write!(f , "expected {} arguments but got {}" , expected , found)
+            }
+            RuntimeErrorKind::BreakOutsideLoop => write!(f , "break outside of a loop"),
+            RuntimeErrorKind::ContinueOutsideLoop => write!(f , "continue outside of a loop"),
+            RuntimeErrorKind::ReturnOutsideFunction => write!(f , "return outside of a function"),
+            RuntimeErrorKind::StackOverflow => write!(f , "too many nested calls"),
+        }
+    }
+
+}
+
+impl fmt::Display for RuntimeError {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f , "{}: {}" , self.span.start , self.kind)
+    }
+
+}
+
+impl fmt::Display for CompileErrorKind {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileErrorKind::IntegerOverflow => write!(f , "integer literal doesn't fit in 64 bits"),
+            CompileErrorKind::TooManyConstants => write!(f , "too many constants in one function"),
+            CompileErrorKind::TooManyLocals => write!(f , "too many local variables in one function"),
+            CompileErrorKind::TooManyArguments => write!(f , "too many arguments in one call"),
+            CompileErrorKind::FunctionTooLong => write!(f , "function is too long"),
+            CompileErrorKind::TooManyUpvalues => write!(f , "too many captured variables in one function"),
+            CompileErrorKind::BreakOutsideLoop => write!(f , "break outside of a loop"),
+            CompileErrorKind::ContinueOutsideLoop => write!(f , "continue outside of a loop"),
+            CompileErrorKind::ReturnOutsideFunction => write!(f , "return outside of a function"),
+        }
+    }
+
+}
+
+impl fmt::Display for CompileError {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f , "{}: {}" , self.span.start , self.kind)
+    }
+
+}
+
+impl fmt::Display for Errors {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Errors::SyntaxError(error) => write!(f , "syntax error at {}" , error),
+            Errors::IoError(message) => write!(f , "could not read the source: {}" , message),
+            Errors::ParseError(error) => write!(f , "parse error at {}" , error),
+            Errors::RuntimeError(error) => write!(f , "runtime error at {}" , error),
+            Errors::CompileError(error) => write!(f , "compile error at {}" , error),
+        }
+    }
+
+}
+
+impl std::error::Error for Errors {}
+
+impl Errors {
+
+    // where in the source text the error is , a lexical error only has a position so its span is empty
+    // IoError has no position
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Errors::SyntaxError(error) => Some(Span::new(error.position , error.position)),
+            Errors::IoError(_) => None,
+            Errors::ParseError(error) => Some(error.span),
+            Errors::RuntimeError(error) => Some(error.span),
+            Errors::CompileError(error) => Some(error.span),
+        }
+    }
+
+}
+
+// returned when text that should name a keyword or an operator doesn't
+#[derive(Debug , Clone , PartialEq)]
+pub struct UnknownToken(pub String);
+
+impl fmt::Display for UnknownToken {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f , "unknown token {:?}" , self.0)
+    }
+
+}
+
+impl std::error::Error for UnknownToken {}
+
+// the mistakes a lexer definition can have (see dfa::MachineBuilder)
+// NoRules     there is nothing to match
+// EmptyMatch  the rule matches the empty text , the machine would never get past it
+#[derive(Debug , Clone , PartialEq , Eq)]
+pub enum BuildError {
+    NoRules,
+    EmptyMatch(String),
+}
+
+impl fmt::Display for BuildError {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::NoRules => write!(f , "the lexer has no rules"),
+            BuildError::EmptyMatch(rule) => write!(f , "the rule {} matches the empty text" , rule),
+        }
+    }
+
+}
+
+impl std::error::Error for BuildError {}
+
+// the mistakes a regex of a token rule can have (see regex::parse)
+// UnclosedGroup       a ( without its )
+// UnmatchedParen      a ) without its (
+// UnclosedClass       a [ without its ]
+// NothingToRepeat     a * , + , ? or {} with nothing before it
+// InvalidEscape       a \ followed by a letter that is not an escape or by nothing
+// InvalidRange        a range of a class that ends before it starts like z-a
+// InvalidRepetition   a {} that is not {n} , {n,} or {n,m} with n <= m <= MAX_REPETITION
+//                     or that makes more than regex::MAX_SIZE nodes
+#[derive(Debug , Clone , PartialEq , Eq)]
+pub enum RegexErrorKind {
+    UnclosedGroup,
+    UnmatchedParen,
+    UnclosedClass,
+    NothingToRepeat,
+    InvalidEscape,
+    InvalidRange,
+    InvalidRepetition,
+}
+
+// position counts the characters of the regex from 0
+#[derive(Debug , Clone , PartialEq , Eq)]
+pub struct RegexError {
+    pub kind: RegexErrorKind,
+    pub position: usize,
+}
+
+impl fmt::Display for RegexErrorKind {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+
+        let message = match self {
+            RegexErrorKind::UnclosedGroup => "unclosed group",
+            RegexErrorKind::UnmatchedParen => "unmatched )",
+            RegexErrorKind::UnclosedClass => "unclosed character class",
+            RegexErrorKind::NothingToRepeat => "nothing to repeat",
+            RegexErrorKind::InvalidEscape => "invalid escape",
+            RegexErrorKind::InvalidRange => "invalid range",
+            RegexErrorKind::InvalidRepetition => "invalid repetition",
+        };
+
+        write!(f , "{}" , message)
+
+    }
+
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f , "invalid regex at {}: {}" , self.position , self.kind)
+    }
+}
+
+impl std::error::Error for RegexError {}
+
+// a location in the source text
+// offset counts bytes from the start of the text
+// line and column start from 1 and column counts characters not bytes
+#[derive(Debug , Clone , Copy , PartialEq , Eq)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Default for Position {
+    fn default() -> Position {
+        Position { offset: 0 , line: 1 , column: 1 }
+    }
+}
+
+impl Position {
+
+    // returns the position right after the input character
+    pub fn advance(&self , input: char) -> Position {
+
+        if input == '\n' {
+            return Position { offset: self.offset + 1 , line: self.line + 1 , column: 1 };
+        }
+
+        Position {
+            offset: self.offset + input.len_utf8(),
+            line: self.line,
+            column: self.column + 1,
+        }
+
+    }
+
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f , "{}:{}" , self.line , self.column)
+    }
+}
+
+// the part of the source text a token came from
+// start is inclusive and end is exclusive so end is the position of the next character
+#[derive(Debug , Clone , Copy , PartialEq , Eq)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position , end: Position) -> Span {
+        Span { start , end }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn position_advance() {
+
+        let mut pos = Position::default();
+
+        for character in "aé\nb".chars() {
+            pos = pos.advance(character);
+        }
+
+        assert_eq!(pos , Position { offset: 5 , line: 2 , column: 2 });
+
+    }
+
+    #[test]
+    fn error_display() {
+
+        let error = Errors::SyntaxError(LexError {
+            kind: LexErrorKind::UnexpectedCharacter,
+            found: Some('@'),
+            state: String::from("Number"),
+            position: Position { offset: 4 , line: 2 , column: 3 },
+        });
+
+        assert_eq!(error.to_string() , "syntax error at 2:3: unexpected character '@' in Number state");
+
+    }
+
+}
@@ -0,0 +1,752 @@
+use std::rc::Rc;
+
+use crate::{
+    bytecode::{Capture , Chunk , CompiledFunction , Instruction},
+    interpreter::{Value , compound_op},
+    parser::{Block , Expr , ExprKind , Literal , Program , Stmt , StmtKind},
+    statemachine::Op,
+    common::{CompileError , CompileErrorKind , Errors , Position , Span},
+};
+
+// a variable that lives in a slot of the call frame , depth is the scope it was declared in
+struct Local {
+    name: String,
+    depth: usize,
+}
+
+// a loop being compiled , start is where the condition is checked
+// depth is the scope around the loop and breaks are the jumps to patch once the loop ends
+struct Loop {
+    start: usize,
+    depth: usize,
+    breaks: Vec<usize>,
+}
+
+// a function being compiled , the top level of the program is one too
+// slot 0 of every call frame holds the function being called
+// so locals starts with it and a function can always call itself by name
+// depth is 0 at the top level where variables are globals , every block adds 1
+struct FunctionState {
+    function: CompiledFunction,
+    locals: Vec<Local>,
+    depth: usize,
+    loops: Vec<Loop>,
+}
+
+impl FunctionState {
+
+    fn new(name: &str , depth: usize) -> FunctionState {
+        FunctionState {
+            function: CompiledFunction { name: String::from(name) , arity: 0 , upvalues: vec![] , chunk: Chunk::default() },
+            locals: vec![Local { name: String::from(name) , depth: 0 }],
+            depth,
+            loops: vec![],
+        }
+    }
+
+}
+
+// where a name was found
+enum Target {
+    Local(u16),
+    Upvalue(u16),
+    Global(u16),
+}
+
+// compiles a program to the function the vm runs first
+// the function returns the value of the last statement if it is an expression like Interpreter::execute
+// a function can use the locals of the functions it is declared in , they become upvalues
+// so closures see the same variables as in the interpreter (see bytecode::Closure)
+pub fn compile(program: &Program) -> Result<Rc<CompiledFunction> , Errors> {
+
+    let mut compiler = Compiler { states: vec![FunctionState::new("" , 0)] };
+    let mut end = Span::new(Position::default() , Position::default());
+
+    for (index , stmt) in program.stmts.iter().enumerate() {
+
+        end = Span::new(stmt.span.end , stmt.span.end);
+
+        match &stmt.kind {
+            StmtKind::Expr(expr) if index + 1 == program.stmts.len() => {
+                compiler.expression(expr)?;
+                compiler.emit(Instruction::Return , stmt.span)?;
+                return Ok(Rc::new(compiler.states.pop().unwrap().function));
+            }
+            _ => compiler.statement(stmt)?,
+        }
+
+    }
+
+    compiler.emit(Instruction::Nil , end)?;
+    compiler.emit(Instruction::Return , end)?;
+
+    Ok(Rc::new(compiler.states.pop().unwrap().function))
+
+}
+
+// states has the function being compiled last and the functions it is declared in before it
+struct Compiler {
+    states: Vec<FunctionState>,
+}
+
+impl Compiler {
+
+    fn state(&mut self) -> &mut FunctionState {
+        self.states.last_mut().unwrap()
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state().function.chunk
+    }
+
+    // adds the instruction and returns its index
+    fn emit(&mut self , instruction: Instruction , span: Span) -> Result<usize , Errors> {
+
+        let chunk = self.chunk();
+
+        // jumps have to be able to reach every instruction and the end of the code
+        // so there can be at most u16::MAX instructions
+        if chunk.code.len() >= u16::MAX as usize {
+            return Err(error(CompileErrorKind::FunctionTooLong , span));
+        }
+
+        chunk.code.push(instruction);
+        chunk.spans.push(span);
+
+        Ok(chunk.code.len() - 1)
+
+    }
+
+    fn constant(&mut self , value: Value , span: Span) -> Result<u16 , Errors> {
+
+        let constants = &mut self.chunk().constants;
+
+        // names are used over and over so they are only stored once
+        if let Value::Str(text) = &value {
+            if let Some(index) = constants.iter().position(|constant| matches!(constant , Value::Str(other) if other == text)) {
+                return Ok(index as u16);
+            }
+        }
+
+        let index = u16::try_from(constants.len()).map_err(|_| error(CompileErrorKind::TooManyConstants , span))?;
+        constants.push(value);
+
+        Ok(index)
+
+    }
+
+    fn name_constant(&mut self , name: &str , span: Span) -> Result<u16 , Errors> {
+        self.constant(Value::Str(Rc::from(name)) , span)
+    }
+
+    // points the jump at the next instruction
+    fn patch(&mut self , jump: usize) -> Result<() , Errors> {
+
+        let chunk = self.chunk();
+        let target = u16::try_from(chunk.code.len()).map_err(|_| error(CompileErrorKind::FunctionTooLong , chunk.spans[jump]))?;
+
+        chunk.code[jump] = match chunk.code[jump] {
+            Instruction::JumpIfFalse(_) => Instruction::JumpIfFalse(target),
+            _ => Instruction::Jump(target),
+        };
+
+        Ok(())
+
+    }
+
+    fn add_local(&mut self , name: &str , span: Span) -> Result<() , Errors> {
+
+        let state = self.state();
+
+        if state.locals.len() > u16::MAX as usize {
+            return Err(error(CompileErrorKind::TooManyLocals , span));
+        }
+
+        let depth = state.depth;
+        state.locals.push(Local { name: String::from(name) , depth });
+
+        Ok(())
+
+    }
+
+    // the value of the variable is on top of the stack
+    fn define(&mut self , name: &str , span: Span) -> Result<() , Errors> {
+
+        if self.state().depth == 0 {
+            let constant = self.name_constant(name , span)?;
+            self.emit(Instruction::DefineGlobal(constant) , span)?;
+            return Ok(());
+        }
+
+        self.add_local(name , span)
+
+    }
+
+    fn resolve(&mut self , name: &str , span: Span) -> Result<Target , Errors> {
+
+        if let Some(slot) = self.state().locals.iter().rposition(|local| local.name == name) {
+            return Ok(Target::Local(slot as u16));
+        }
+
+        if let Some(index) = self.resolve_upvalue(self.states.len() - 1 , name , span)? {
+            return Ok(Target::Upvalue(index));
+        }
+
+        Ok(Target::Global(self.name_constant(name , span)?))
+
+    }
+
+    // looks for the name in the functions around states[function] and returns the upvalue of
+    // that function it is , the functions in between capture it too so it is passed down to it
+    // slot 0 is the enclosing function itself so a function declared in another can call it
+    fn resolve_upvalue(&mut self , function: usize , name: &str , span: Span) -> Result<Option<u16> , Errors> {
+
+        if function == 0 {
+            return Ok(None);
+        }
+
+        let enclosing = &self.states[function - 1];
+
+        let capture = match enclosing.locals.iter().rposition(|local| local.name == name) {
+            Some(slot) => Capture::Local(slot as u16),
+            None => match self.resolve_upvalue(function - 1 , name , span)? {
+                Some(index) => Capture::Upvalue(index),
+                None => return Ok(None),
+            },
+        };
+
+        let upvalues = &mut self.states[function].function.upvalues;
+
+        if let Some(index) = upvalues.iter().position(|&other| other == capture) {
+            return Ok(Some(index as u16));
+        }
+
+        let index = u16::try_from(upvalues.len()).map_err(|_| error(CompileErrorKind::TooManyUpvalues , span))?;
+        upvalues.push(capture);
+
+        Ok(Some(index))
+
+    }
+
+    // emits a Pop for every local declared deeper than depth , the locals stay declared
+    fn pop_locals(&mut self , depth: usize , span: Span) -> Result<() , Errors> {
+
+        let count = self.state().locals.iter().filter(|local| local.depth > depth).count();
+
+        for _ in 0..count {
+            self.emit(Instruction::Pop , span)?;
+        }
+
+        Ok(())
+
+    }
+
+    fn statement(&mut self , stmt: &Stmt) -> Result<() , Errors> {
+
+        let span = stmt.span;
+
+        match &stmt.kind {
+
+            StmtKind::Let { name , value } => {
+
+                match value {
+                    Some(value) => self.expression(value)?,
+                    None => {
+                        self.emit(Instruction::Nil , span)?;
+                    }
+                }
+
+                self.define(name , span)?;
+
+            }
+
+            StmtKind::Fn { name , params , body } => {
+
+                self.function(name , params , body , span)?;
+
+                // the name is already a local , see declare_functions
+                let state = self.state();
+
+                match state.locals.iter().rposition(|local| local.name == *name && local.depth == state.depth) {
+                    Some(slot) if state.depth > 0 => {
+                        self.emit(Instruction::SetLocal(slot as u16) , span)?;
+                        self.emit(Instruction::Pop , span)?;
+                    }
+                    _ => self.define(name , span)?,
+                }
+
+            }
+
+            StmtKind::Return(value) => {
+
+                if self.states.len() == 1 {
+                    return Err(error(CompileErrorKind::ReturnOutsideFunction , span));
+                }
+
+                match value {
+                    Some(value) => self.expression(value)?,
+                    None => {
+                        self.emit(Instruction::Nil , span)?;
+                    }
+                }
+
+                self.emit(Instruction::Return , span)?;
+
+            }
+
+            StmtKind::Expr(expr) => {
+                self.expression(expr)?;
+                self.emit(Instruction::Pop , span)?;
+            }
+
+            StmtKind::If { .. } => self.if_chain(stmt)?,
+
+            StmtKind::While { condition , body } => {
+
+                let start = self.chunk().code.len();
+                self.expression(condition)?;
+                let exit = self.emit(Instruction::JumpIfFalse(0) , span)?;
+
+                let depth = self.state().depth;
+                self.state().loops.push(Loop { start , depth , breaks: vec![] });
+
+                self.block(body)?;
+                self.emit(Instruction::Jump(start as u16) , span)?;
+                self.patch(exit)?;
+
+                for jump in self.state().loops.pop().unwrap().breaks {
+                    self.patch(jump)?;
+                }
+
+            }
+
+            StmtKind::Block(block) => self.block(block)?,
+
+            StmtKind::Break => {
+
+                let depth = match self.state().loops.last() {
+                    Some(current) => current.depth,
+                    None => return Err(error(CompileErrorKind::BreakOutsideLoop , span)),
+                };
+
+                self.pop_locals(depth , span)?;
+                let jump = self.emit(Instruction::Jump(0) , span)?;
+                self.state().loops.last_mut().unwrap().breaks.push(jump);
+
+            }
+
+            StmtKind::Continue => {
+
+                let (start , depth) = match self.state().loops.last() {
+                    Some(current) => (current.start , current.depth),
+                    None => return Err(error(CompileErrorKind::ContinueOutsideLoop , span)),
+                };
+
+                self.pop_locals(depth , span)?;
+                self.emit(Instruction::Jump(start as u16) , span)?;
+
+            }
+
+        }
+
+        Ok(())
+
+    }
+
+    // an if and the else ifs in its else branch , they are compiled in a loop
+    // so a long chain doesn't recurse , every branch that runs jumps to the end
+    fn if_chain(&mut self , stmt: &Stmt) -> Result<() , Errors> {
+
+        let mut current = stmt;
+        let mut ends = vec![];
+
+        loop {
+
+            let (condition , then_branch , else_branch) = match &current.kind {
+                StmtKind::If { condition , then_branch , else_branch } => (condition , then_branch , else_branch),
+                // the else at the end
+                _ => {
+                    self.statement(current)?;
+                    break;
+                }
+            };
+
+            self.expression(condition)?;
+            let skip_then = self.emit(Instruction::JumpIfFalse(0) , current.span)?;
+            self.block(then_branch)?;
+
+            match else_branch {
+                Some(else_branch) => {
+                    ends.push(self.emit(Instruction::Jump(0) , current.span)?);
+                    self.patch(skip_then)?;
+                    current = else_branch;
+                }
+                None => {
+                    self.patch(skip_then)?;
+                    break;
+                }
+            }
+
+        }
+
+        for jump in ends {
+            self.patch(jump)?;
+        }
+
+        Ok(())
+
+    }
+
+    // the statements get a scope of their own and their locals are popped at the end
+    fn block(&mut self , block: &Block) -> Result<() , Errors> {
+
+        self.state().depth += 1;
+        self.declare_functions(&block.stmts)?;
+
+        for stmt in &block.stmts {
+            self.statement(stmt)?;
+        }
+
+        let end = Span::new(block.span.end , block.span.end);
+        let depth = self.state().depth - 1;
+
+        self.pop_locals(depth , end)?;
+
+        let state = self.state();
+        state.locals.retain(|local| local.depth <= depth);
+        state.depth = depth;
+
+        Ok(())
+
+    }
+
+    // makes a nil local for every function declared in the statements before any of them is compiled
+    // so the functions of a block can call the ones declared after them like in the interpreter
+    // the fn statement sets the local , at the top level the functions are globals and need nothing
+    fn declare_functions(&mut self , stmts: &[Stmt]) -> Result<() , Errors> {
+
+        for stmt in stmts {
+
+            let name = match &stmt.kind {
+                StmtKind::Fn { name , .. } => name,
+                _ => continue,
+            };
+
+            let state = self.state();
+
+            if state.locals.iter().any(|local| local.name == *name && local.depth == state.depth) {
+                continue;
+            }
+
+            self.emit(Instruction::Nil , stmt.span)?;
+            self.add_local(name , stmt.span)?;
+
+        }
+
+        Ok(())
+
+    }
+
+    // compiles the function and pushes it as a constant
+    fn function(&mut self , name: &str , params: &[String] , body: &Block , span: Span) -> Result<() , Errors> {
+
+        if params.len() > u8::MAX as usize {
+            return Err(error(CompileErrorKind::TooManyArguments , span));
+        }
+
+        self.states.push(FunctionState::new(name , 1));
+
+        for param in params {
+            self.add_local(param , span)?;
+        }
+
+        self.declare_functions(&body.stmts)?;
+
+        for stmt in &body.stmts {
+            self.statement(stmt)?;
+        }
+
+        // a function that doesn't return returns nil
+        let end = Span::new(body.span.end , body.span.end);
+        self.emit(Instruction::Nil , end)?;
+        self.emit(Instruction::Return , end)?;
+
+        let mut function = self.states.pop().unwrap().function;
+        function.arity = params.len();
+
+        let captures = !function.upvalues.is_empty();
+        let constant = self.constant(Value::Compiled(Rc::new(function)) , span)?;
+
+        match captures {
+            true => self.emit(Instruction::Closure(constant) , span)?,
+            false => self.emit(Instruction::Constant(constant) , span)?,
+        };
+
+        Ok(())
+
+    }
+
+    fn expression(&mut self , expr: &Expr) -> Result<() , Errors> {
+
+        let span = expr.span;
+
+        match &expr.kind {
+
+            ExprKind::Literal(literal) => {
+
+                let value = match literal {
+                    Literal::Number(value) => Value::Int(*value),
+                    Literal::Float(value) => Value::Float(*value),
+                    Literal::BigInt(_) => return Err(error(CompileErrorKind::IntegerOverflow , span)),
+                    Literal::Text(text) => Value::Str(Rc::from(text.as_str())),
+                    Literal::Bool(true) => return self.emit(Instruction::True , span).map(|_| ()),
+                    Literal::Bool(false) => return self.emit(Instruction::False , span).map(|_| ()),
+                    Literal::Nil => return self.emit(Instruction::Nil , span).map(|_| ()),
+                };
+
+                let constant = self.constant(value , span)?;
+                self.emit(Instruction::Constant(constant) , span)?;
+
+            }
+
+            ExprKind::Ident(name) => {
+                let instruction = match self.resolve(name , span)? {
+                    Target::Local(slot) => Instruction::GetLocal(slot),
+                    Target::Upvalue(index) => Instruction::GetUpvalue(index),
+                    Target::Global(constant) => Instruction::GetGlobal(constant),
+                };
+                self.emit(instruction , span)?;
+            }
+
+            ExprKind::Grouping(inner) => self.expression(inner)?,
+
+            ExprKind::Unary { op , operand } => {
+                self.expression(operand)?;
+                self.emit(Instruction::Unary(*op) , span)?;
+            }
+
+            // a && b is false unless a is true and then it is whether b is true
+            ExprKind::Binary { op: Op::AndAnd , left , right } => {
+                self.expression(left)?;
+                let short = self.emit(Instruction::JumpIfFalse(0) , span)?;
+                self.expression(right)?;
+                self.emit(Instruction::ToBool , span)?;
+                let end = self.emit(Instruction::Jump(0) , span)?;
+                self.patch(short)?;
+                self.emit(Instruction::False , span)?;
+                self.patch(end)?;
+            }
+
+            // a || b is true if a is true and otherwise it is whether b is true
+            ExprKind::Binary { op: Op::OrOr , left , right } => {
+                self.expression(left)?;
+                let long = self.emit(Instruction::JumpIfFalse(0) , span)?;
+                self.emit(Instruction::True , span)?;
+                let end = self.emit(Instruction::Jump(0) , span)?;
+                self.patch(long)?;
+                self.expression(right)?;
+                self.emit(Instruction::ToBool , span)?;
+                self.patch(end)?;
+            }
+
+            ExprKind::Binary { op , left , right } => {
+                self.expression(left)?;
+                self.expression(right)?;
+                self.emit(Instruction::Binary(*op) , span)?;
+            }
+
+            ExprKind::Assign { name , op , value } => {
+
+                let (get , set) = match self.resolve(name , span)? {
+                    Target::Local(slot) => (Instruction::GetLocal(slot) , Instruction::SetLocal(slot)),
+                    Target::Upvalue(index) => (Instruction::GetUpvalue(index) , Instruction::SetUpvalue(index)),
+                    Target::Global(constant) => (Instruction::GetGlobal(constant) , Instruction::SetGlobal(constant)),
+                };
+
+                match compound_op(*op) {
+                    Some(op) => {
+                        self.emit(get , span)?;
+                        self.expression(value)?;
+                        self.emit(Instruction::Binary(op) , span)?;
+                    }
+                    None => self.expression(value)?,
+                }
+
+                self.emit(set , span)?;
+
+            }
+
+            ExprKind::Call { callee , args } => {
+
+                self.expression(callee)?;
+
+                for arg in args {
+                    self.expression(arg)?;
+                }
+
+                let count = u8::try_from(args.len()).map_err(|_| error(CompileErrorKind::TooManyArguments , span))?;
+                self.emit(Instruction::Call(count) , span)?;
+
+            }
+
+        }
+
+        Ok(())
+
+    }
+
+}
+
+fn error(kind: CompileErrorKind , span: Span) -> Errors {
+    Errors::CompileError(CompileError { kind , span })
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{lexer::{ImagineLexer , Lexer} , parser::Parser , statemachine::ImagineMachine};
+
+    fn compile_text(text: &str) -> Result<Rc<CompiledFunction> , Errors> {
+        let tokens = ImagineLexer::new(String::from(text) , ImagineMachine::new()).lex()?;
+        compile(&Parser::new(tokens).parse_program()?)
+    }
+
+    fn error_of(text: &str) -> CompileError {
+        match compile_text(text).unwrap_err() {
+            Errors::CompileError(error) => error,
+            error => panic!("unexpected error {:?}" , error),
+        }
+    }
+
+    #[test]
+    fn disassemble() {
+
+        let function = compile_text("let x = 1\nfn double(n) { return n * 2 }\ndouble(x + 2.5)").unwrap();
+
+        assert_eq!(function.disassemble() , [
+            "== <script> ==",
+            "0000     1:9  Constant          0 ; 1",
+            "0001       |  DefineGlobal      1 ; \"x\"",
+            "0002     2:1  Constant          2 ; <fn double>",
+            "0003       |  DefineGlobal      3 ; \"double\"",
+            "0004     3:1  GetGlobal         3 ; \"double\"",
+            "0005       |  GetGlobal         1 ; \"x\"",
+            "0006       |  Constant          4 ; 2.5",
+            "0007       |  Binary            +",
+            "0008       |  Call              1",
+            "0009       |  Return",
+            "",
+            "== double ==",
+            "0000    2:23  GetLocal          1",
+            "0001       |  Constant          0 ; 2",
+            "0002       |  Binary            *",
+            "0003       |  Return",
+            "0004       |  Nil",
+            "0005       |  Return",
+            "",
+        ].join("\n"));
+
+    }
+
+    #[test]
+    fn locals_and_jumps() {
+
+        let function = compile_text("{ let a = 1 while a { let b = a break } a = 2 }").unwrap();
+
+        assert_eq!(function.chunk.code , vec![
+            Instruction::Constant(0),
+            Instruction::GetLocal(1),
+            Instruction::JumpIfFalse(8),
+            Instruction::GetLocal(1),
+            // the break pops b before it jumps out
+            Instruction::Pop,
+            Instruction::Jump(8),
+            Instruction::Pop,
+            Instruction::Jump(1),
+            Instruction::Constant(1),
+            Instruction::SetLocal(1),
+            Instruction::Pop,
+            Instruction::Pop,
+            Instruction::Nil,
+            Instruction::Return,
+        ]);
+
+    }
+
+    #[test]
+    fn upvalues() {
+
+        let function = compile_text("fn outer(x) { let y = 2 fn middle() { fn inner() { return x + y + x } return inner } return middle }").unwrap();
+
+        // every function here declares one function
+        let declared = |function: &CompiledFunction| {
+            function.chunk.constants.iter().find_map(|constant| match constant {
+                Value::Compiled(inner) => Some(inner.clone()),
+                _ => None,
+            }).unwrap()
+        };
+
+        let outer = declared(&function);
+        let middle = declared(&outer);
+        let inner = declared(&middle);
+
+        // middle is declared before y so y is in slot 3 of outer
+        assert!(outer.upvalues.is_empty());
+        assert_eq!(middle.upvalues , vec![Capture::Local(1) , Capture::Local(3)]);
+        assert_eq!(inner.upvalues , vec![Capture::Upvalue(0) , Capture::Upvalue(1)]);
+
+        assert_eq!(&inner.chunk.code[..5] , [
+            Instruction::GetUpvalue(0),
+            Instruction::GetUpvalue(1),
+            Instruction::Binary(Op::Plus),
+            Instruction::GetUpvalue(0),
+            Instruction::Binary(Op::Plus),
+        ]);
+
+        // a function that captures nothing stays a constant
+        let makes_closure = |function: &CompiledFunction| function.chunk.code.iter().any(|instruction| matches!(instruction , Instruction::Closure(_)));
+
+        assert!(makes_closure(&outer) && makes_closure(&middle));
+        assert!(!makes_closure(&function));
+
+    }
+
+    #[test]
+    fn function_length() {
+
+        let span = Span::new(Position::default() , Position::default());
+        let mut compiler = Compiler { states: vec![FunctionState::new("" , 0)] };
+
+        for _ in 0..u16::MAX - 1 {
+            compiler.emit(Instruction::Nil , span).unwrap();
+        }
+
+        // the last instruction that fits , a jump past it still has a u16 target
+        let jump = compiler.emit(Instruction::Jump(0) , span).unwrap();
+        compiler.patch(jump).unwrap();
+        assert_eq!(compiler.chunk().code[jump] , Instruction::Jump(u16::MAX));
+
+        match compiler.emit(Instruction::Nil , span).unwrap_err() {
+            Errors::CompileError(error) => assert_eq!(error.kind , CompileErrorKind::FunctionTooLong),
+            error => panic!("unexpected error {:?}" , error),
+        }
+
+        // every statement is a Nil and a Pop
+        assert_eq!(error_of(&"nil ".repeat(u16::MAX as usize / 2 + 1)).kind , CompileErrorKind::FunctionTooLong);
+
+    }
+
+    #[test]
+    fn compile_errors() {
+
+        assert_eq!(error_of("break").kind , CompileErrorKind::BreakOutsideLoop);
+        assert_eq!(error_of("while true { fn f() { continue } }").kind , CompileErrorKind::ContinueOutsideLoop);
+        assert_eq!(error_of("return").kind , CompileErrorKind::ReturnOutsideFunction);
+
+        // globals and the function itself are fine
+        assert!(compile_text("let g = 1 fn outer() { fn inner() { return inner(g) } return outer }").is_ok());
+
+    }
+
+}
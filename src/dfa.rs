@@ -0,0 +1,1072 @@
+use std::{cmp::Reverse , collections::HashMap , rc::Rc};
+
+use crate::{
+    common::{BuildError , Errors , LexError , LexErrorKind , Position , RegexError , Span},
+    statemachine::{CustomToken , Keyword , Machine , SpannedToken , Token},
+};
+
+// lexers declared as rules instead of hand written states
+//
+// let machine = MachineBuilder::new()
+//     .token("number" , Pattern::class(CharClass::ascii_digit()).plus())
+//     .token("name" , Pattern::class(CharClass::ascii_alphabetic()).plus())
+//     .keywords(["if" , "else"])
+//     .literals("op" , ["+" , "-" , "=="])
+//     .skip(Pattern::regex(r"\s+|#[^\n]*")?)
+//     .build()?;
+//
+// the rules become an nfa which becomes a dfa that is then minimized
+// the machine takes the longest text any rule matches
+// and if several rules match it the one with the highest priority wins , then the first one
+
+// a set of characters , ranges is sorted and no two ranges touch , the ends are included
+#[derive(Debug , Clone , PartialEq , Eq , Default)]
+pub struct CharClass {
+    ranges: Vec<(char , char)>,
+}
+
+// the character right after or right before , chars skip the surrogates
+fn after(input: char) -> Option<char> {
+    match input {
+        '\u{D7FF}' => Some('\u{E000}'),
+        _ => char::from_u32(input as u32 + 1),
+    }
+}
+
+fn before(input: char) -> Option<char> {
+    match input {
+        '\0' => None,
+        '\u{E000}' => Some('\u{D7FF}'),
+        _ => char::from_u32(input as u32 - 1),
+    }
+}
+
+impl CharClass {
+
+    // the class with no characters
+    pub fn new() -> CharClass {
+        CharClass::default()
+    }
+
+    pub fn range(first: char , last: char) -> CharClass {
+        CharClass::from_ranges(vec![(first.min(last) , first.max(last))])
+    }
+
+    // every character of the text
+    pub fn chars(text: &str) -> CharClass {
+        CharClass::from_ranges(text.chars().map(|input| (input , input)).collect())
+    }
+
+    pub fn any() -> CharClass {
+        CharClass::range('\0' , char::MAX)
+    }
+
+    pub fn ascii_digit() -> CharClass {
+        CharClass::range('0' , '9')
+    }
+
+    pub fn ascii_alphabetic() -> CharClass {
+        CharClass::range('a' , 'z').union(&CharClass::range('A' , 'Z'))
+    }
+
+    pub fn ascii_alphanumeric() -> CharClass {
+        CharClass::ascii_alphabetic().union(&CharClass::ascii_digit())
+    }
+
+    // the ascii whitespace , space , tab , the line breaks and form feed
+    pub fn whitespace() -> CharClass {
+        CharClass::chars(" \t\n\r\x0b\x0c")
+    }
+
+    // sorts the ranges and merges the ones that overlap or touch
+    fn from_ranges(mut ranges: Vec<(char , char)>) -> CharClass {
+
+        ranges.sort_unstable();
+
+        let mut merged: Vec<(char , char)> = Vec::with_capacity(ranges.len());
+
+        for (first , last) in ranges {
+            match merged.last_mut() {
+                Some(previous) if after(previous.1).is_none_or(|next| first <= next) => previous.1 = previous.1.max(last),
+                _ => merged.push((first , last)),
+            }
+        }
+
+        CharClass { ranges: merged }
+
+    }
+
+    pub fn union(&self , other: &CharClass) -> CharClass {
+        CharClass::from_ranges(self.ranges.iter().chain(&other.ranges).copied().collect())
+    }
+
+    // every character that is not in the class
+    pub fn negate(&self) -> CharClass {
+
+        let mut ranges = vec![];
+        let mut next = Some('\0');
+
+        for &(first , last) in &self.ranges {
+
+            if let Some(start) = next {
+                if start < first {
+                    ranges.push((start , before(first).unwrap()));
+                }
+            }
+
+            next = after(last);
+
+        }
+
+        if let Some(start) = next {
+            ranges.push((start , char::MAX));
+        }
+
+        CharClass { ranges }
+
+    }
+
+    pub fn contains(&self , input: char) -> bool {
+        self.ranges
+            .binary_search_by(|&(first , last)| {
+                if last < input {
+                    std::cmp::Ordering::Less
+                } else if first > input {
+                    std::cmp::Ordering::Greater
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn ranges(&self) -> &[(char , char)] {
+        &self.ranges
+    }
+
+}
+
+// the text a rule matches
+// Star is zero or more of the pattern , Plus one or more
+#[derive(Debug , Clone , PartialEq)]
+pub enum Pattern {
+    Literal(String),
+    Class(CharClass),
+    Concat(Vec<Pattern>),
+    Alternate(Vec<Pattern>),
+    Star(Box<Pattern>),
+    Plus(Box<Pattern>),
+    Optional(Box<Pattern>),
+}
+
+impl Pattern {
+
+    pub fn literal(text: &str) -> Pattern {
+        Pattern::Literal(String::from(text))
+    }
+
+    pub fn class(class: CharClass) -> Pattern {
+        Pattern::Class(class)
+    }
+
+    // the pattern of a regex like [a-z_]\w* (see regex::parse for the syntax)
+    pub fn regex(regex: &str) -> Result<Pattern , RegexError> {
+        crate::regex::parse(regex)
+    }
+
+    // the pattern followed by the next one
+    pub fn then(self , next: Pattern) -> Pattern {
+        match self {
+            Pattern::Concat(mut patterns) => {
+                patterns.push(next);
+                Pattern::Concat(patterns)
+            }
+            pattern => Pattern::Concat(vec![pattern , next]),
+        }
+    }
+
+    // the pattern or the other one
+    pub fn or(self , other: Pattern) -> Pattern {
+        match self {
+            Pattern::Alternate(mut patterns) => {
+                patterns.push(other);
+                Pattern::Alternate(patterns)
+            }
+            pattern => Pattern::Alternate(vec![pattern , other]),
+        }
+    }
+
+    pub fn star(self) -> Pattern {
+        Pattern::Star(Box::new(self))
+    }
+
+    pub fn plus(self) -> Pattern {
+        Pattern::Plus(Box::new(self))
+    }
+
+    pub fn optional(self) -> Pattern {
+        Pattern::Optional(Box::new(self))
+    }
+
+    pub fn matches_empty(&self) -> bool {
+        match self {
+            Pattern::Literal(text) => text.is_empty(),
+            Pattern::Class(_) => false,
+            Pattern::Concat(patterns) => patterns.iter().all(Pattern::matches_empty),
+            Pattern::Alternate(patterns) => patterns.iter().any(Pattern::matches_empty),
+            Pattern::Star(_) | Pattern::Optional(_) => true,
+            Pattern::Plus(pattern) => pattern.matches_empty(),
+        }
+    }
+
+}
+
+// what a rule makes of the text it matches
+// Custom   a Token::Custom named after the rule
+// Keyword  a Token::Keyword
+// Skip     nothing , for whitespace and comments
+// Map      the token the function makes of the text , so a dialect can reuse the tokens of the parser
+#[derive(Clone)]
+enum Emit {
+    Custom,
+    Keyword,
+    Skip,
+    Map(fn(&str) -> Result<Token , LexErrorKind>),
+}
+
+// a pattern , what to make of the text it matches and its priority
+// the keyword rules have a priority of 1 so they win over a rule for names , the rest have 0
+#[derive(Clone)]
+pub struct Rule {
+    name: String,
+    pattern: Pattern,
+    emit: Emit,
+    priority: i32,
+}
+
+impl Rule {
+
+    pub fn token(kind: &str , pattern: Pattern) -> Rule {
+        Rule { name: String::from(kind) , pattern , emit: Emit::Custom , priority: 0 }
+    }
+
+    pub fn skip(pattern: Pattern) -> Rule {
+        Rule { name: String::from("skip") , pattern , emit: Emit::Skip , priority: 0 }
+    }
+
+    pub fn keywords<I , K>(keywords: I) -> Rule
+    where I: IntoIterator<Item = K> , K: AsRef<str> {
+        let pattern = Pattern::Alternate(keywords.into_iter().map(|keyword| Pattern::literal(keyword.as_ref())).collect());
+        Rule { name: String::from("keyword") , pattern , emit: Emit::Keyword , priority: 1 }
+    }
+
+    // the function turns the text into a token or fails with the error to report
+    pub fn map(name: &str , pattern: Pattern , map: fn(&str) -> Result<Token , LexErrorKind>) -> Rule {
+        Rule { name: String::from(name) , pattern , emit: Emit::Map(map) , priority: 0 }
+    }
+
+    pub fn priority(mut self , priority: i32) -> Rule {
+        self.priority = priority;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+}
+
+// collects the rules of a lexer and builds its machine (see the example at the top)
+#[derive(Clone , Default)]
+pub struct MachineBuilder {
+    rules: Vec<Rule>,
+}
+
+impl MachineBuilder {
+
+    pub fn new() -> MachineBuilder {
+        MachineBuilder::default()
+    }
+
+    pub fn rule(mut self , rule: Rule) -> MachineBuilder {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn token(self , kind: &str , pattern: Pattern) -> MachineBuilder {
+        self.rule(Rule::token(kind , pattern))
+    }
+
+    // one rule for all the texts , like the operators of a language
+    pub fn literals<I , K>(self , kind: &str , literals: I) -> MachineBuilder
+    where I: IntoIterator<Item = K> , K: AsRef<str> {
+        let pattern = Pattern::Alternate(literals.into_iter().map(|literal| Pattern::literal(literal.as_ref())).collect());
+        self.rule(Rule::token(kind , pattern))
+    }
+
+    pub fn keywords<I , K>(self , keywords: I) -> MachineBuilder
+    where I: IntoIterator<Item = K> , K: AsRef<str> {
+        self.rule(Rule::keywords(keywords))
+    }
+
+    pub fn skip(self , pattern: Pattern) -> MachineBuilder {
+        self.rule(Rule::skip(pattern))
+    }
+
+    pub fn map(self , name: &str , pattern: Pattern , map: fn(&str) -> Result<Token , LexErrorKind>) -> MachineBuilder {
+        self.rule(Rule::map(name , pattern , map))
+    }
+
+    pub fn build(&self) -> Result<DfaMachine , BuildError> {
+
+        if self.rules.is_empty() {
+            return Err(BuildError::NoRules);
+        }
+
+        if let Some(rule) = self.rules.iter().find(|rule| rule.pattern.matches_empty()) {
+            return Err(BuildError::EmptyMatch(rule.name.clone()));
+        }
+
+        let nfa = Nfa::new(&self.rules);
+        let dfa = Dfa::new(&nfa , self.rules.clone());
+
+        Ok(DfaMachine::new(Rc::new(dfa)))
+
+    }
+
+}
+
+// a state of the nfa , it moves on the characters of a class or on nothing (epsilon)
+// accept is the rule that matched if the state ends one
+#[derive(Default)]
+struct NfaState {
+    epsilon: Vec<usize>,
+    edges: Vec<(CharClass , usize)>,
+    accept: Option<usize>,
+}
+
+// the rules as one nfa , state 0 starts every rule
+struct Nfa {
+    states: Vec<NfaState>,
+}
+
+impl Nfa {
+
+    fn new(rules: &[Rule]) -> Nfa {
+
+        let mut nfa = Nfa { states: vec![NfaState::default()] };
+
+        for (index , rule) in rules.iter().enumerate() {
+            let start = nfa.add();
+            nfa.states[0].epsilon.push(start);
+            let end = nfa.pattern(&rule.pattern , start);
+            nfa.states[end].accept = Some(index);
+        }
+
+        nfa
+
+    }
+
+    fn add(&mut self) -> usize {
+        self.states.push(NfaState::default());
+        self.states.len() - 1
+    }
+
+    fn edge(&mut self , from: usize , class: CharClass) -> usize {
+        let to = self.add();
+        self.states[from].edges.push((class , to));
+        to
+    }
+
+    // adds the states of the pattern after start and returns the state it ends in
+    // (see https://en.wikipedia.org/wiki/Thompson%27s_construction)
+    fn pattern(&mut self , pattern: &Pattern , start: usize) -> usize {
+
+        match pattern {
+
+            Pattern::Literal(text) => text.chars().fold(start , |from , input| self.edge(from , CharClass::range(input , input))),
+
+            Pattern::Class(class) => self.edge(start , class.clone()),
+
+            Pattern::Concat(patterns) => patterns.iter().fold(start , |from , pattern| self.pattern(pattern , from)),
+
+            Pattern::Alternate(patterns) => {
+
+                let end = self.add();
+
+                for pattern in patterns {
+                    let branch = self.add();
+                    self.states[start].epsilon.push(branch);
+                    let branch_end = self.pattern(pattern , branch);
+                    self.states[branch_end].epsilon.push(end);
+                }
+
+                end
+
+            }
+
+            // start -> inner -> pattern -> end , the pattern can loop back to inner for Star and Plus
+            // and start can skip to end for Star and Optional
+            Pattern::Star(inner_pattern) | Pattern::Plus(inner_pattern) | Pattern::Optional(inner_pattern) => {
+
+                let inner = self.add();
+                let end = self.add();
+
+                self.states[start].epsilon.push(inner);
+                let inner_end = self.pattern(inner_pattern , inner);
+                self.states[inner_end].epsilon.push(end);
+
+                if !matches!(pattern , Pattern::Optional(_)) {
+                    self.states[inner_end].epsilon.push(inner);
+                }
+
+                if !matches!(pattern , Pattern::Plus(_)) {
+                    self.states[start].epsilon.push(end);
+                }
+
+                end
+
+            }
+
+        }
+
+    }
+
+    // the states reachable from the states without reading a character , sorted
+    fn closure(&self , mut stack: Vec<usize>) -> Vec<usize> {
+
+        let mut seen = vec![false ; self.states.len()];
+        let mut states = vec![];
+
+        while let Some(state) = stack.pop() {
+
+            if seen[state] {
+                continue;
+            }
+
+            seen[state] = true;
+            states.push(state);
+            stack.extend(&self.states[state].epsilon);
+
+        }
+
+        states.sort_unstable();
+        states
+
+    }
+
+}
+
+// a state of the dfa , transitions are sorted ranges of characters and the state they go to
+// accept is the rule that matches the text read so far if any
+struct DfaState {
+    transitions: Vec<(char , char , usize)>,
+    accept: Option<usize>,
+}
+
+// the transitions with the same targets in the same order become one
+fn merge_ranges(transitions: impl IntoIterator<Item = (char , char , usize)>) -> Vec<(char , char , usize)> {
+
+    let mut merged: Vec<(char , char , usize)> = vec![];
+
+    for (first , last , target) in transitions {
+        match merged.last_mut() {
+            Some(previous) if previous.2 == target && after(previous.1) == Some(first) => previous.1 = last,
+            _ => merged.push((first , last , target)),
+        }
+    }
+
+    merged
+
+}
+
+// merges the states no text can tell apart (see https://en.wikipedia.org/wiki/DFA_minimization)
+// the states start out grouped by the rule they accept and a group is split
+// until all its states go to the same groups on every character
+// the group of a state is its number in the smaller dfa so the start state stays 0
+fn minimize(states: Vec<DfaState>) -> Vec<DfaState> {
+
+    let mut accepts = HashMap::new();
+    let mut groups: Vec<usize> = states
+        .iter()
+        .map(|state| {
+            let next = accepts.len();
+            *accepts.entry(state.accept).or_insert(next)
+        })
+        .collect();
+    let mut count = accepts.len();
+
+    loop {
+
+        let mut signatures = HashMap::new();
+        let next: Vec<usize> = states
+            .iter()
+            .enumerate()
+            .map(|(index , state)| {
+                let transitions = merge_ranges(state.transitions.iter().map(|&(first , last , target)| (first , last , groups[target])));
+                let next = signatures.len();
+                *signatures.entry((groups[index] , transitions)).or_insert(next)
+            })
+            .collect();
+
+        groups = next;
+
+        // a split always makes more groups so the same number means nothing was split
+        if signatures.len() == count {
+            break;
+        }
+
+        count = signatures.len();
+
+    }
+
+    let mut minimized: Vec<Option<DfaState>> = (0..count).map(|_| None).collect();
+
+    for (index , state) in states.iter().enumerate() {
+        minimized[groups[index]].get_or_insert_with(|| DfaState {
+            transitions: merge_ranges(state.transitions.iter().map(|&(first , last , target)| (first , last , groups[target]))),
+            accept: state.accept,
+        });
+    }
+
+    minimized.into_iter().map(Option::unwrap).collect()
+
+}
+
+// there is no transition for the character in the ascii table
+const NO_STATE: u32 = u32::MAX;
+
+// the tables of a DfaMachine , state 0 is the start state
+// ascii has the transitions of every state for the ascii characters , 128 of them a state
+// so most characters are one lookup , the rest are looked up in the ranges of the state
+struct Dfa {
+    states: Vec<DfaState>,
+    ascii: Vec<u32>,
+    rules: Vec<Rule>,
+}
+
+impl Dfa {
+
+    // every dfa state is a set of nfa states
+    // (see https://en.wikipedia.org/wiki/Powerset_construction)
+    fn new(nfa: &Nfa , rules: Vec<Rule>) -> Dfa {
+
+        // the characters are split into the ranges no class splits further
+        // so every character of a range goes to the same states
+        let mut bounds: Vec<char> = nfa.states
+            .iter()
+            .flat_map(|state| &state.edges)
+            .flat_map(|(class , _)| class.ranges())
+            .flat_map(|&(first , last)| [Some(first) , after(last)])
+            .flatten()
+            .collect();
+
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        let pieces: Vec<(char , char)> = bounds
+            .iter()
+            .enumerate()
+            .map(|(index , &first)| match bounds.get(index + 1) {
+                Some(&next) => (first , before(next).unwrap()),
+                None => (first , char::MAX),
+            })
+            .collect();
+
+        let start = nfa.closure(vec![0]);
+        let mut ids = HashMap::from([(start.clone() , 0)]);
+        let mut sets = vec![start];
+        let mut states = vec![];
+
+        while states.len() < sets.len() {
+
+            let set = sets[states.len()].clone();
+            let mut transitions: Vec<(char , char , usize)> = vec![];
+
+            for &(first , last) in &pieces {
+
+                let targets: Vec<usize> = set
+                    .iter()
+                    .flat_map(|&state| &nfa.states[state].edges)
+                    .filter(|(class , _)| class.contains(first))
+                    .map(|&(_ , target)| target)
+                    .collect();
+
+                if targets.is_empty() {
+                    continue;
+                }
+
+                let target_set = nfa.closure(targets);
+                let target = *ids.entry(target_set.clone()).or_insert_with(|| {
+                    sets.push(target_set);
+                    sets.len() - 1
+                });
+
+                transitions.push((first , last , target));
+
+            }
+
+            // the best rule wins , then the first one
+            let accept = set
+                .iter()
+                .filter_map(|&state| nfa.states[state].accept)
+                .min_by_key(|&rule| (Reverse(rules[rule].priority) , rule));
+
+            states.push(DfaState { transitions: merge_ranges(transitions) , accept });
+
+        }
+
+        let states = minimize(states);
+        let mut ascii = vec![NO_STATE ; states.len() * 128];
+
+        for (index , state) in states.iter().enumerate() {
+            for &(first , last , target) in state.transitions.iter().filter(|&&(first , _ , _)| first.is_ascii()) {
+                for input in first as usize..=(last as usize).min(127) {
+                    ascii[index * 128 + input] = target as u32;
+                }
+            }
+        }
+
+        Dfa { states , ascii , rules }
+
+    }
+
+    fn next(&self , state: usize , input: char) -> Option<usize> {
+
+        if input.is_ascii() {
+            let target = self.ascii[state * 128 + input as usize];
+            return (target != NO_STATE).then_some(target as usize);
+        }
+
+        let transitions = &self.states[state].transitions;
+
+        let index = transitions.partition_point(|&(_ , last , _)| last < input);
+
+        match transitions.get(index) {
+            Some(&(first , _ , target)) if first <= input => Some(target),
+            _ => None,
+        }
+
+    }
+
+}
+
+// runs the dfa of a MachineBuilder , it is a Machine so ImagineLexer can use it
+// buffer holds the characters of the token being read with their positions
+// accepted is the length of the longest text a rule matched so far and the rule
+// the machine reads on past a match as long as a longer one is possible
+// and gives back what it read past the match when there is none (see Machine::unread)
+// the dfa is shared so cloning a machine is cheap
+#[derive(Clone)]
+pub struct DfaMachine {
+    dfa: Rc<Dfa>,
+    state: usize,
+    buffer: Vec<(char , Position)>,
+    accepted: Option<(usize , usize)>,
+    unread: Vec<(char , Position)>,
+}
+
+impl DfaMachine {
+
+    fn new(dfa: Rc<Dfa>) -> DfaMachine {
+        DfaMachine { dfa , state: 0 , buffer: vec![] , accepted: None , unread: vec![] }
+    }
+
+    pub fn state_count(&self) -> usize {
+        self.dfa.states.len()
+    }
+
+    // the rule the text read so far matches , for error messages
+    fn state_name(&self) -> String {
+        match self.dfa.states[self.state].accept {
+            Some(rule) => self.dfa.rules[rule].name.clone(),
+            None if self.buffer.is_empty() => String::from("Start"),
+            None => String::from("Unfinished"),
+        }
+    }
+
+    fn error(&self , kind: LexErrorKind , found: Option<char> , position: Position) -> Errors {
+        Errors::SyntaxError(LexError { kind , found , state: self.state_name() , position })
+    }
+
+    // the token of the rule for the first length characters of the buffer , None for skipped text
+    fn token(&self , length: usize , rule: usize , end: Position) -> Result<Option<SpannedToken> , Errors> {
+
+        let text: String = self.buffer[..length].iter().map(|&(input , _)| input).collect();
+        let start = self.buffer[0].1;
+        let rule = &self.dfa.rules[rule];
+
+        let token = match rule.emit {
+            Emit::Custom => Token::Custom(Box::new(CustomToken { kind: rule.name.clone() , text })),
+            Emit::Keyword => Token::Keyword(text.parse().unwrap_or(Keyword::Custom(text))),
+            Emit::Skip => return Ok(None),
+            Emit::Map(map) => map(&text).map_err(|kind| self.error(kind , None , start))?,
+        };
+
+        Ok(Some(SpannedToken { token , span: Span::new(start , end) }))
+
+    }
+
+    // emits the longest match , gives back what comes after it and starts over
+    // on error the machine is left as it was
+    fn emit(&mut self , length: usize , rule: usize , end: Position) -> Result<Option<SpannedToken> , Errors> {
+
+        let token = self.token(length , rule , end)?;
+
+        self.unread.extend(self.buffer.drain(length..));
+        self.buffer.clear();
+        self.state = 0;
+        self.accepted = None;
+
+        Ok(token)
+
+    }
+
+}
+
+impl Machine for DfaMachine {
+
+    fn get_final_token(&self , end: Position) -> Result<SpannedToken , Errors> {
+
+        let blank = SpannedToken { token: Token::Blank , span: Span::new(end , end) };
+
+        let (length , rule) = match self.accepted {
+            _ if self.buffer.is_empty() => return Ok(blank),
+            Some((length , rule)) if length == self.buffer.len() => (length , rule),
+            // flush gives back what comes after a match so there is no match here
+            _ => return Err(self.error(LexErrorKind::UnexpectedEnd , None , self.buffer[0].1)),
+        };
+
+        Ok(self.token(length , rule , end)?.unwrap_or(blank))
+
+    }
+
+    fn feed(&mut self , input: char , pos: Position) -> Result<Option<SpannedToken> , Errors> {
+
+        if let Some(next) = self.dfa.next(self.state , input) {
+
+            self.state = next;
+            self.buffer.push((input , pos));
+
+            if let Some(rule) = self.dfa.states[next].accept {
+                self.accepted = Some((self.buffer.len() , rule));
+            }
+
+            return Ok(None);
+
+        }
+
+        // no rule goes on with the input so the longest match is the token
+        // and the input is given back with the rest to start the next one
+        let (length , rule) = match self.accepted {
+            Some(accepted) => accepted,
+            None => return Err(self.error(LexErrorKind::UnexpectedCharacter , Some(input) , pos)),
+        };
+
+        let end = self.buffer.get(length).map_or(pos , |&(_ , next)| next);
+        let token = self.emit(length , rule , end)?;
+        self.unread.push((input , pos));
+
+        Ok(token)
+
+    }
+
+    fn reset(&mut self) -> Option<(String , Position)> {
+
+        self.state = 0;
+        self.accepted = None;
+        self.unread.clear();
+
+        let start = self.buffer.first()?.1;
+        let text = self.buffer.drain(..).map(|(input , _)| input).collect();
+
+        Some((text , start))
+
+    }
+
+    fn unread(&mut self) -> Vec<(char , Position)> {
+        std::mem::take(&mut self.unread)
+    }
+
+    fn flush(&mut self) -> Result<Option<SpannedToken> , Errors> {
+
+        match self.accepted {
+            Some((length , rule)) if length < self.buffer.len() => {
+                let end = self.buffer[length].1;
+                self.emit(length , rule , end)
+            }
+            _ => Ok(None),
+        }
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{
+        lexer::{ImagineLexer , Lexer},
+        parser::{Parser , Expr , ExprKind},
+        statemachine::Op,
+    };
+
+    fn lex(machine: &DfaMachine , text: &str) -> Result<Vec<Token> , Errors> {
+        let tokens = ImagineLexer::new(String::from(text) , machine.clone()).lex()?;
+        Ok(tokens.into_iter().map(|token| token.token).collect())
+    }
+
+    // a small language with names , numbers , keywords , operators and comments
+    fn dialect() -> DfaMachine {
+
+        let name = Pattern::class(CharClass::ascii_alphabetic().union(&CharClass::chars("_")))
+            .then(Pattern::class(CharClass::ascii_alphanumeric().union(&CharClass::chars("_"))).star());
+
+        MachineBuilder::new()
+            .token("name" , name)
+            .token("number" , Pattern::class(CharClass::ascii_digit()).plus())
+            .keywords(["let" , "if" , "unless"])
+            .literals("op" , ["=" , "==" , "=>" , "+" , "(" , ")"])
+            .skip(Pattern::class(CharClass::whitespace()).plus())
+            .skip(Pattern::literal("#").then(Pattern::class(CharClass::chars("\n").negate()).star()))
+            .build()
+            .unwrap()
+
+    }
+
+    #[test]
+    fn char_classes() {
+
+        let class = CharClass::chars("cab").union(&CharClass::range('x' , 'z')).union(&CharClass::range('d' , 'e'));
+        assert_eq!(class.ranges() , [('a' , 'e') , ('x' , 'z')]);
+        assert!(class.contains('c') && class.contains('z') && !class.contains('f'));
+
+        let negated = class.negate();
+        assert_eq!(negated.ranges() , [('\0' , '`') , ('f' , 'w') , ('{' , char::MAX)]);
+        assert!(negated.negate() == class && CharClass::any().negate().is_empty());
+
+        // the ranges on both sides of the surrogates touch
+        assert_eq!(CharClass::range('a' , '\u{D7FF}').union(&CharClass::range('\u{E000}' , 'ｚ')).ranges() , [('a' , 'ｚ')]);
+
+    }
+
+    #[test]
+    fn dialect_tokens() {
+
+        let machine = dialect();
+
+        assert_eq!(lex(&machine , "let letter = 12 # a comment\nif (x == 1) => unless+").unwrap() , vec![
+            Token::Keyword(Keyword::Let),
+            Token::custom("name" , "letter"),
+            Token::custom("op" , "="),
+            Token::custom("number" , "12"),
+            Token::Keyword(Keyword::If),
+            Token::custom("op" , "("),
+            Token::custom("name" , "x"),
+            Token::custom("op" , "=="),
+            Token::custom("number" , "1"),
+            Token::custom("op" , ")"),
+            Token::custom("op" , "=>"),
+            Token::Keyword(Keyword::Custom(String::from("unless"))),
+            Token::custom("op" , "+"),
+        ]);
+
+        let tokens = ImagineLexer::new(String::from("ab  ==\n1") , machine.clone()).lex().unwrap();
+        let spans: Vec<String> = tokens.iter().map(|token| format!("{}-{}" , token.span.start , token.span.end)).collect();
+        assert_eq!(spans , ["1:1-1:3" , "1:5-1:7" , "2:1-2:2"]);
+
+        let error = match lex(&machine , "x = 1 @ 2").unwrap_err() {
+            Errors::SyntaxError(error) => error,
+            error => panic!("unexpected error {:?}" , error),
+        };
+        assert_eq!((error.kind , error.found , error.position.column) , (LexErrorKind::UnexpectedCharacter , Some('@') , 7));
+
+        let (tokens , errors) = ImagineLexer::new(String::from("a @@ b") , machine).lex_recovering();
+        let tokens: Vec<Token> = tokens.into_iter().map(|token| token.token).collect();
+        assert_eq!(tokens , vec![Token::custom("name" , "a") , Token::Error(String::from("@@")) , Token::custom("name" , "b")]);
+        assert_eq!(errors.len() , 1);
+
+    }
+
+    #[test]
+    fn backtracking() {
+
+        // the machine reads ahead for ... and has to give back what it read when it is not there
+        let machine = MachineBuilder::new()
+            .literals("dots" , ["." , "..."])
+            .literals("word" , ["ab" , "abcd" , "c"])
+            .skip(Pattern::literal(" "))
+            .build()
+            .unwrap();
+
+        let dots = |count: usize| Token::custom("dots" , &".".repeat(count));
+
+        assert_eq!(lex(&machine , "..").unwrap() , vec![dots(1) , dots(1)]);
+        assert_eq!(lex(&machine , ".... ..").unwrap() , vec![dots(3) , dots(1) , dots(1) , dots(1)]);
+        assert_eq!(lex(&machine , "abcab abc").unwrap() , vec![
+            Token::custom("word" , "ab"),
+            Token::custom("word" , "c"),
+            Token::custom("word" , "ab"),
+            Token::custom("word" , "ab"),
+            Token::custom("word" , "c"),
+        ]);
+
+        // abc then d fails after c was given back and read again
+        let (tokens , errors) = ImagineLexer::new(String::from("abcx") , machine.clone()).lex_recovering();
+        let tokens: Vec<Token> = tokens.into_iter().map(|token| token.token).collect();
+        assert_eq!(tokens , vec![Token::custom("word" , "ab") , Token::custom("word" , "c") , Token::Error(String::from("x"))]);
+        assert_eq!(errors.len() , 1);
+
+        // the delimiter the lexer resyncs at is read again and is an error of its own
+        let (tokens , errors) = ImagineLexer::new(String::from("a( c") , machine.clone()).lex_recovering();
+        let tokens: Vec<Token> = tokens.into_iter().map(|token| token.token).collect();
+        assert_eq!(tokens , vec![Token::Error(String::from("a")) , Token::Error(String::from("(")) , Token::custom("word" , "c")]);
+        assert_eq!(errors.len() , 2);
+
+        // a token that can only be finished by characters that never come
+        let machine = MachineBuilder::new().token("text" , Pattern::literal("\"").then(Pattern::class(CharClass::chars("\"").negate()).star()).then(Pattern::literal("\""))).build().unwrap();
+        match lex(&machine , "\"open").unwrap_err() {
+            Errors::SyntaxError(error) => assert_eq!((error.kind , error.position.column) , (LexErrorKind::UnexpectedEnd , 1)),
+            error => panic!("unexpected error {:?}" , error),
+        }
+
+    }
+
+    #[test]
+    fn priorities_and_mapped_tokens() {
+
+        let word = || Pattern::class(CharClass::ascii_alphabetic()).plus();
+
+        // the first rule wins a tie unless another one has a higher priority
+        let first = MachineBuilder::new().token("a" , word()).token("b" , word()).build().unwrap();
+        let second = MachineBuilder::new().token("a" , word()).rule(Rule::token("b" , word()).priority(2)).build().unwrap();
+
+        assert_eq!(lex(&first , "x").unwrap() , vec![Token::custom("a" , "x")]);
+        assert_eq!(lex(&second , "x").unwrap() , vec![Token::custom("b" , "x")]);
+
+        // any priority works , even the lowest one
+        let lowest = MachineBuilder::new().rule(Rule::token("a" , word()).priority(i32::MIN)).token("b" , word()).build().unwrap();
+        assert_eq!(lex(&lowest , "x").unwrap() , vec![Token::custom("b" , "x")]);
+
+        // rules can make the tokens of the parser so a dialect can still be parsed
+        let machine = MachineBuilder::new()
+            .map("number" , Pattern::class(CharClass::ascii_digit()).plus() , |text| {
+                text.parse().map(Token::Number).map_err(|_| LexErrorKind::IntegerOverflow)
+            })
+            .map("op" , Pattern::literal("plus").or(Pattern::literal("times")) , |text| match text {
+                "plus" => Ok(Token::Operator(Op::Plus)),
+                _ => Ok(Token::Operator(Op::Star)),
+            })
+            .skip(Pattern::literal(" "))
+            .build()
+            .unwrap();
+
+        let tokens = ImagineLexer::new(String::from("1 plus 2 times 3") , machine.clone()).lex().unwrap();
+        let expression = Parser::new(tokens).parse_expression().unwrap();
+
+        let ExprKind::Binary { op: Op::Plus , right , .. } = expression.kind else { panic!("{:?}" , expression) };
+        assert!(matches!(*right , Expr { kind: ExprKind::Binary { op: Op::Star , .. } , .. }));
+
+        match lex(&machine , "1 plus 99999999999999999999").unwrap_err() {
+            Errors::SyntaxError(error) => assert_eq!((error.kind , error.position.column) , (LexErrorKind::IntegerOverflow , 8)),
+            error => panic!("unexpected error {:?}" , error),
+        }
+
+    }
+
+    #[test]
+    fn build_errors() {
+
+        assert_eq!(MachineBuilder::new().build().err() , Some(BuildError::NoRules));
+        assert_eq!(
+            MachineBuilder::new().token("x" , Pattern::literal("x")).token("spaces" , Pattern::literal(" ").star()).build().err(),
+            Some(BuildError::EmptyMatch(String::from("spaces"))),
+        );
+
+        // == and => accept the same rule and can't go on so they are one state
+        let machine = MachineBuilder::new().literals("op" , ["=" , "==" , "=>"]).build().unwrap();
+        assert_eq!(machine.state_count() , 3);
+
+    }
+
+    #[test]
+    fn minimized_tables() {
+
+        // the textbook example , the minimal dfa has 4 states
+        let machine = MachineBuilder::new().token("x" , Pattern::regex("(a|b)*abb").unwrap()).build().unwrap();
+        assert_eq!(machine.state_count() , 4);
+        assert_eq!(lex(&machine , "babaabb").unwrap() , vec![Token::custom("x" , "babaabb")]);
+
+        // states that end different rules are never merged
+        let machine = MachineBuilder::new()
+            .token("a" , Pattern::regex("x").unwrap())
+            .token("b" , Pattern::regex("y").unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(machine.state_count() , 3);
+
+        // characters past ascii use the ranges instead of the table
+        let machine = MachineBuilder::new().token("greek" , Pattern::regex("[α-ω]+").unwrap()).build().unwrap();
+        assert_eq!(lex(&machine , "λόγος").unwrap_err().to_string() , "syntax error at 1:2: unexpected character 'ό' in Start state");
+        assert_eq!(lex(&machine , "λογος").unwrap() , vec![Token::custom("greek" , "λογος")]);
+
+    }
+
+    #[test]
+    fn regex_config_format() {
+
+        // a lexer for ini files declared with regexes
+        let machine = MachineBuilder::new()
+            .token("section" , Pattern::regex(r"\[[\w.]+\]").unwrap())
+            .token("key" , Pattern::regex(r"[A-Za-z_][\w-]*").unwrap())
+            .token("equals" , Pattern::regex("=").unwrap())
+            .token("number" , Pattern::regex(r"-?\d+(\.\d+)?([eE][+-]?\d{1,3})?").unwrap())
+            .token("string" , Pattern::regex(r#""([^"\\\n]|\\.)*""#).unwrap())
+            // bool matches words the key rule matches too so it needs the higher priority
+            .rule(Rule::token("bool" , Pattern::regex("true|false|yes|no").unwrap()).priority(1))
+            .skip(Pattern::regex(r"[ \t\r\n]+|[;#][^\n]*").unwrap())
+            .build()
+            .unwrap();
+
+        let text = "[server.main]\nport = 8080 ; the port\nname = \"a \\\"b\\\"\"\nratio=-1.5e-3\nenabled = yes\nyesterday = true";
+
+        let tokens: Vec<(String , String)> = lex(&machine , text)
+            .unwrap()
+            .into_iter()
+            .map(|token| match token {
+                Token::Custom(custom) => (custom.kind , custom.text),
+                token => panic!("unexpected token {:?}" , token),
+            })
+            .collect();
+
+        let expected = [
+            ("section" , "[server.main]"),
+            ("key" , "port"),
+            ("equals" , "="),
+            ("number" , "8080"),
+            ("key" , "name"),
+            ("equals" , "="),
+            ("string" , "\"a \\\"b\\\"\""),
+            ("key" , "ratio"),
+            ("equals" , "="),
+            ("number" , "-1.5e-3"),
+            ("key" , "enabled"),
+            ("equals" , "="),
+            ("bool" , "yes"),
+            // the longest match still wins over a priority
+            ("key" , "yesterday"),
+            ("equals" , "="),
+            ("bool" , "true"),
+        ];
+
+        let expected: Vec<(String , String)> = expected.iter().map(|&(kind , text)| (String::from(kind) , String::from(text))).collect();
+        assert_eq!(tokens , expected);
+
+    }
+
+}
@@ -0,0 +1,158 @@
+use std::{
+    collections::{BTreeMap , HashSet , VecDeque},
+    fmt::Write,
+};
+
+use crate::{
+    common::Position,
+    statemachine::{ImagineMachine , Machine , State},
+};
+
+// the states the graph shows , the number , string and comment details are left out
+// so the graph stays readable
+fn is_shown(state: State) -> bool {
+    matches!(state , State::Blank | State::Number | State::Float | State::Ident | State::Operator | State::Lpar | State::Rpar | State::Lbr | State::Rbr)
+}
+
+// the characters the machine is fed to find its transitions , one or a few of every kind of input
+// x , b , o and e are here for the number prefixes and exponents
+const SAMPLES: &[char] = &[
+    '0' , '1' , 'a' , 'b' , 'e' , 'o' , 'x' , '_' , '.',
+    '+' , '-' , '*' , '/' , '=' , '!' , '<' , '>' , '&' , '|' , '^' , '~',
+    '(' , ')' , '{' , '}' , '[' , ']' , ',' , ';' , ':' , '"' , ' ' , '\n',
+];
+
+// a new machine fed the input , None if the input is not valid
+fn replay(input: &str) -> Option<ImagineMachine> {
+
+    let mut machine = ImagineMachine::new();
+    let mut position = Position::default();
+
+    for input in input.chars() {
+        machine.feed(input , position).ok()?;
+        position = position.advance(input);
+    }
+
+    Some(machine)
+
+}
+
+fn label(input: char) -> String {
+    match input {
+        ' ' => String::from("space"),
+        '\n' => String::from("newline"),
+        '"' => String::from("\\\""),
+        input => input.to_string(),
+    }
+}
+
+// the transitions between the shown states as a graphviz digraph , render it with
+// dot -Tsvg graph.dot > graph.svg
+//
+// the states are found breadth first from Blank by feeding every sample to every state reached
+// a state is explored once with the first input that reached it , except for Operator
+// which is explored for every operator since what it accepts depends on the operator so far
+// the dashed edges emit a token
+pub fn transition_graph() -> String {
+
+    // (from , to , emits) -> the samples that make the transition
+    let mut edges: BTreeMap<(&str , &str , bool) , Vec<char>> = BTreeMap::new();
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([String::new()]);
+
+    seen.insert((State::Blank , String::new()));
+
+    while let Some(path) = queue.pop_front() {
+
+        // only paths that replay without errors are queued
+        let from = replay(&path).unwrap().get_state();
+
+        for &input in SAMPLES {
+
+            // feed changes the machine so every sample starts from a new one
+            let mut machine = replay(&path).unwrap();
+            let position = Position { offset: path.len() , line: 1 , column: path.chars().count() + 1 };
+
+            let emits = match machine.feed(input , position) {
+                Ok(token) => token.is_some(),
+                Err(_) => continue,
+            };
+
+            let to = machine.get_state();
+
+            if !is_shown(to) {
+                continue;
+            }
+
+            let inputs = edges.entry((from.get_type() , to.get_type() , emits)).or_default();
+
+            if !inputs.contains(&input) {
+                inputs.push(input);
+            }
+
+            let key = match to {
+                State::Operator => (to , String::from(machine.get_value())),
+                _ => (to , String::new()),
+            };
+
+            if seen.insert(key) {
+                queue.push_back(format!("{}{}" , path , input));
+            }
+
+        }
+
+    }
+
+    let mut dot = String::from("digraph ImagineMachine {\n    rankdir=LR;\n    node [shape=circle];\n    Blank [shape=doublecircle];\n");
+
+    for ((from , to , emits) , inputs) in edges {
+
+        let labels: Vec<String> = inputs.into_iter().map(label).collect();
+        let style = if emits { " , style=dashed" } else { "" };
+
+        writeln!(dot , "    {} -> {} [label=\"{}\"{}];" , from , to , labels.join(" ") , style).unwrap();
+
+    }
+
+    dot.push_str("}\n");
+    dot
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn graph() {
+
+        let dot = transition_graph();
+        let lines: Vec<&str> = dot.lines().collect();
+
+        assert_eq!(&lines[..4] , ["digraph ImagineMachine {" , "    rankdir=LR;" , "    node [shape=circle];" , "    Blank [shape=doublecircle];"]);
+        assert_eq!(lines.last() , Some(&"}"));
+
+        for edge in [
+            "    Blank -> Blank [label=\"space newline\"];",
+            "    Blank -> Number [label=\"0 1\"];",
+            "    Number -> Float [label=\".\"];",
+            "    Number -> Number [label=\"0 1 _\"];",
+            "    Ident -> Ident [label=\"0 1 a b e o x _\"];",
+            "    Ident -> Lpar [label=\"(\" , style=dashed];",
+            "    Rpar -> Rbr [label=\"}\" , style=dashed];",
+        ] {
+            assert!(lines.contains(&edge) , "{} is not in\n{}" , edge , dot);
+        }
+
+        // the operators only grow while they are operators , then they emit
+        let operator = lines.iter().find(|line| line.starts_with("    Operator -> Operator [label")).unwrap();
+        assert!(!operator.contains("style") && operator.contains('=') && operator.contains('&'));
+        assert!(lines.iter().any(|line| line.starts_with("    Operator -> Operator") && line.ends_with("style=dashed];")));
+
+        // the states that are left out don't show up
+        assert!(!dot.contains("Text") && !dot.contains("Comment") && !dot.contains("RadixNumber"));
+
+    }
+
+}
@@ -0,0 +1,1058 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    io::{Stdout , Write},
+    rc::{Rc , Weak},
+};
+
+use crate::{
+    bytecode::{Closure , CompiledFunction},
+    statemachine::{ImagineMachine , Op , SpannedToken},
+    lexer::{ImagineLexer , Lexer},
+    parser::{Block , Expr , ExprKind , Literal , Parser , Program , Stmt , StmtKind},
+    common::{Errors , RuntimeError , RuntimeErrorKind , Span},
+};
+
+// the values an Imagine program works with
+// functions are values too so they can be passed around and called later
+// Function is a function of the interpreter with the scope it was declared in
+// the scope is only left out while the function is stored in that scope (see Env::define)
+// Compiled and Closure are functions of the vm (see vm::Vm)
+#[derive(Debug , Clone)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(Rc<str>),
+    Nil,
+    Function(Rc<Function> , Option<Rc<Env>>),
+    Compiled(Rc<CompiledFunction>),
+    Closure(Rc<Closure>),
+    Builtin(Builtin),
+}
+
+// a function declared with fn , closure is the scope it was declared in
+// the scope holds the function so the function only points back at it weakly
+// and the values of the function keep the scope alive
+pub struct Function {
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Block,
+    closure: Weak<Env>,
+}
+
+// the functions every program starts with
+// print writes its arguments separated by spaces and ends the line
+#[derive(Debug , Clone , Copy , PartialEq , Eq)]
+pub enum Builtin {
+    Print,
+}
+
+impl Builtin {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Print => "print",
+        }
+    }
+
+    // the interpreter and the vm both call builtins through this
+    pub fn call(&self , args: &[Value] , output: &mut impl Write) -> Result<Value , Errors> {
+
+        match self {
+            Builtin::Print => {
+
+                let line: Vec<String> = args.iter().map(Value::to_string).collect();
+
+                writeln!(output , "{}" , line.join(" ")).map_err(|error| Errors::IoError(error.to_string()))?;
+
+                Ok(Value::Nil)
+
+            }
+        }
+
+    }
+
+}
+
+impl fmt::Debug for Function {
+
+    // the closure may hold the function itself so it is left out
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f , "<fn {}>" , self.name)
+    }
+
+}
+
+impl Value {
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::Str(_) => "string",
+            Value::Nil => "nil",
+            Value::Function(..) | Value::Compiled(_) | Value::Closure(_) | Value::Builtin(_) => "function",
+        }
+    }
+
+    // nil and false are false , every other value is true
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self , Value::Nil | Value::Bool(false))
+    }
+
+}
+
+// ints and floats are equal if they have the same value and functions only equal themselves
+impl PartialEq for Value {
+
+    fn eq(&self , other: &Value) -> bool {
+        match (self , other) {
+            (Value::Int(left) , Value::Int(right)) => left == right,
+            (Value::Float(left) , Value::Float(right)) => left == right,
+            (Value::Int(left) , Value::Float(right)) | (Value::Float(right) , Value::Int(left)) => *left as f64 == *right,
+            (Value::Bool(left) , Value::Bool(right)) => left == right,
+            (Value::Str(left) , Value::Str(right)) => left == right,
+            (Value::Nil , Value::Nil) => true,
+            (Value::Function(left , _) , Value::Function(right , _)) => Rc::ptr_eq(left , right),
+            (Value::Compiled(left) , Value::Compiled(right)) => Rc::ptr_eq(left , right),
+            (Value::Closure(left) , Value::Closure(right)) => Rc::ptr_eq(left , right),
+            (Value::Builtin(left) , Value::Builtin(right)) => left == right,
+            _ => false,
+        }
+    }
+
+}
+
+// how print shows a value
+impl fmt::Display for Value {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f , "{}" , value),
+            Value::Float(value) => write!(f , "{:?}" , value),
+            Value::Bool(value) => write!(f , "{}" , value),
+            Value::Str(text) => write!(f , "{}" , text),
+            Value::Nil => write!(f , "nil"),
+            Value::Function(function , _) => write!(f , "<fn {}>" , function.name),
+            Value::Compiled(function) => write!(f , "<fn {}>" , function.name),
+            Value::Closure(closure) => write!(f , "<fn {}>" , closure.function.name),
+            Value::Builtin(builtin) => write!(f , "<builtin {}>" , builtin.name()),
+        }
+    }
+
+}
+
+// a scope , names are looked up in the scope they are used in and then in the enclosing ones
+//
+// a function stored in the scope it was declared in is stored without the scope
+// and gets it back when it is read , the scope holding itself would never be freed
+// a function stored in a scope further out still holds its scope , like a closure
+// assigned to a variable of an enclosing function , so that scope lives as long as the variable
+// when the variable is in one of the scopes around the function's own they hold each other
+// and release frees them once nothing else does (see Interpreter::leave)
+pub struct Env {
+    values: RefCell<HashMap<String , Value>>,
+    parent: Option<Rc<Env>>,
+}
+
+impl fmt::Debug for Env {
+
+    // the values may hold the scope itself so they are left out
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f , "<scope>")
+    }
+
+}
+
+impl Env {
+
+    fn new(parent: Option<Rc<Env>>) -> Rc<Env> {
+        Rc::new(Env { values: RefCell::new(HashMap::new()) , parent })
+    }
+
+    // the value as it is stored in this scope
+    fn unbind(&self , value: Value) -> Value {
+        match value {
+            Value::Function(function , _) if std::ptr::eq(function.closure.as_ptr() , self) => Value::Function(function , None),
+            value => value,
+        }
+    }
+
+    fn define(&self , name: &str , value: Value) {
+        let value = self.unbind(value);
+        self.values.borrow_mut().insert(String::from(name) , value);
+    }
+
+    fn get(&self , name: &str) -> Option<Value> {
+
+        if let Some(value) = self.values.borrow().get(name) {
+
+            // this is the scope of a function stored without it so it is still alive
+            if let Value::Function(function , None) = value {
+                return Some(Value::Function(function.clone() , function.closure.upgrade()));
+            }
+
+            return Some(value.clone());
+
+        }
+
+        self.parent.as_ref().and_then(|parent| parent.get(name))
+
+    }
+
+    // whether the scope is this one or one inside it
+    fn contains(self: &Rc<Env> , scope: &Rc<Env>) -> bool {
+
+        let mut current = Some(scope);
+
+        while let Some(env) = current {
+
+            if Rc::ptr_eq(env , self) {
+                return true;
+            }
+
+            current = env.parent.as_ref();
+
+        }
+
+        false
+
+    }
+
+    // frees a scope that was left once only its own values hold it , the caller's scope
+    // is the only reference to it that is not counted
+    // a function of a scope inside this one that is stored in this one holds its scope and that
+    // holds this one as a parent , so the scopes reached through the values that way are found
+    // and if every other reference to them comes from the values or parents of the others
+    // nothing can get to them anymore and they are emptied so they are freed
+    // returns whether the scope is still held and its values hold it too
+    fn release(scope: &Rc<Env>) -> bool {
+
+        // nothing else holds the scope so it is freed anyway
+        if Rc::strong_count(scope) == 1 {
+            return false;
+        }
+
+        // found holds one reference to each and held counts the ones from inside
+        let mut found = vec![scope.clone()];
+        let mut held: HashMap<*const Env , usize> = HashMap::new();
+        let mut index = 0;
+
+        while index < found.len() {
+
+            let closures: Vec<Rc<Env>> = found[index].values.borrow().values().filter_map(|value| match value {
+                Value::Function(_ , Some(closure)) if scope.contains(closure) => Some(closure.clone()),
+                _ => None,
+            }).collect();
+
+            for closure in closures {
+
+                *held.entry(Rc::as_ptr(&closure)).or_default() += 1;
+
+                // the scopes between the function's one and this one hold their parents
+                let mut current = closure;
+
+                while !Rc::ptr_eq(&current , scope) {
+
+                    let parent = current.parent.clone().unwrap();
+
+                    if !found.iter().any(|env| Rc::ptr_eq(env , &current)) {
+                        *held.entry(Rc::as_ptr(&parent)).or_default() += 1;
+                        found.push(current);
+                    }
+
+                    current = parent;
+
+                }
+
+            }
+
+            index += 1;
+
+        }
+
+        let unreachable = found.iter().all(|env| {
+            let outside = if Rc::ptr_eq(env , scope) { 2 } else { 1 };
+            Rc::strong_count(env) == held.get(&Rc::as_ptr(env)).copied().unwrap_or(0) + outside
+        });
+
+        if !unreachable {
+            return !held.is_empty();
+        }
+
+        for env in &found {
+            let values = std::mem::take(&mut *env.values.borrow_mut());
+            drop(values);
+        }
+
+        false
+
+    }
+
+    // changes the closest variable with the name , returns false if there is none
+    fn assign(&self , name: &str , value: Value) -> bool {
+
+        if let Some(slot) = self.values.borrow_mut().get_mut(name) {
+            *slot = self.unbind(value);
+            return true;
+        }
+
+        match &self.parent {
+            Some(parent) => parent.assign(name , value),
+            None => false,
+        }
+
+    }
+
+}
+
+// how a statement finished , break , continue and return unwind to the loop or call that handles them
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+// how much of the rust stack a program can use before it is stopped
+// calls and nested expressions recurse on the rust stack and what a call takes depends on the build
+// (about 32KiB in a debug build and 4KiB in a release build) so the depth is limited by the stack
+// it uses instead of a count , this fits in the 2MiB stack of a spawned thread with room to spare
+// a program run on a thread with a smaller stack needs a smaller one (see Interpreter::with_stack_budget)
+pub const STACK_BUDGET: usize = 1536 * 1024;
+
+// how many scopes Interpreter::leave keeps before it checks them again
+const SUSPECTS: usize = 64;
+
+// about where the rust stack is , the address of a local
+fn stack_position() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+// runs programs one statement at a time straight from the AST
+// globals keeps the variables between runs so a REPL can build on what it ran before
+// output is where print writes to
+// loops and calls count the loops and function calls that are running
+// to catch a break outside of a loop or a return outside of a function
+// stack_start is where the rust stack was when the program started
+// and stack_budget is how much more it can use (see STACK_BUDGET)
+// suspects are the scopes that were left while something held them and their values held them too
+// they are checked again when there are suspects_limit of them and after every program
+pub struct Interpreter<W: Write = Stdout> {
+    globals: Rc<Env>,
+    output: W,
+    loops: usize,
+    calls: usize,
+    stack_start: usize,
+    stack_budget: usize,
+    suspects: Vec<Weak<Env>>,
+    suspects_limit: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Interpreter {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Interpreter {
+        Interpreter::with_output(std::io::stdout())
+    }
+}
+
+// a closure stored in a global holds its scope and the scope holds the globals again
+// so the globals are emptied to free them with the interpreter
+impl<W: Write> Drop for Interpreter<W> {
+    fn drop(&mut self) {
+        let values = std::mem::take(&mut *self.globals.values.borrow_mut());
+        drop(values);
+        self.collect();
+    }
+}
+
+impl<W: Write> Interpreter<W> {
+
+    pub fn with_output(output: W) -> Interpreter<W> {
+
+        let globals = Env::new(None);
+        globals.define(Builtin::Print.name() , Value::Builtin(Builtin::Print));
+
+        Interpreter { globals , output , loops: 0 , calls: 0 , stack_start: 0 , stack_budget: STACK_BUDGET , suspects: vec![] , suspects_limit: SUSPECTS }
+
+    }
+
+    // how much of the rust stack a program can use , it has to leave room for what the
+    // thread running the interpreter already uses and for the last call that goes over it
+    pub fn with_stack_budget(mut self , stack_budget: usize) -> Interpreter<W> {
+        self.stack_budget = stack_budget;
+        self
+    }
+
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    // lets a host write to the same place as print , like a REPL showing results
+    pub fn output_mut(&mut self) -> &mut W {
+        &mut self.output
+    }
+
+    // lexes , parses and runs the source text
+    pub fn run_text(&mut self , text: &str) -> Result<Value , Errors> {
+        let tokens = ImagineLexer::new(String::from(text) , ImagineMachine::new()).lex()?;
+        self.run(tokens)
+    }
+
+    // parses and runs the tokens of a lexer
+    pub fn run(&mut self , tokens: Vec<SpannedToken>) -> Result<Value , Errors> {
+        let program = Parser::new(tokens).parse_program()?;
+        self.execute(&program)
+    }
+
+    // runs the program in the global scope
+    // returns the value of the last statement if it is an expression , nil otherwise
+    pub fn execute(&mut self , program: &Program) -> Result<Value , Errors> {
+
+        self.stack_start = stack_position();
+
+        let result = self.execute_program(program);
+        self.collect();
+
+        result
+
+    }
+
+    fn execute_program(&mut self , program: &Program) -> Result<Value , Errors> {
+
+        let globals = self.globals.clone();
+        let mut last = Value::Nil;
+
+        for stmt in &program.stmts {
+
+            if let StmtKind::Expr(expr) = &stmt.kind {
+                last = self.eval(expr , &globals)?;
+                continue;
+            }
+
+            // execute only returns Normal at the top , the rest are errors
+            self.execute_stmt(stmt , &globals)?;
+            last = Value::Nil;
+
+        }
+
+        Ok(last)
+
+    }
+
+    fn execute_stmt(&mut self , stmt: &Stmt , env: &Rc<Env>) -> Result<Flow , Errors> {
+
+        match &stmt.kind {
+
+            StmtKind::Let { name , value } => {
+
+                let value = match value {
+                    Some(value) => self.eval(value , env)?,
+                    None => Value::Nil,
+                };
+
+                env.define(name , value);
+
+            }
+
+            StmtKind::Fn { name , params , body } => {
+
+                let function = Function {
+                    name: name.clone(),
+                    params: params.clone(),
+                    body: body.clone(),
+                    closure: Rc::downgrade(env),
+                };
+
+                env.define(name , Value::Function(Rc::new(function) , None));
+
+            }
+
+            StmtKind::Return(value) => {
+
+                if self.calls == 0 {
+                    return Err(error(RuntimeErrorKind::ReturnOutsideFunction , stmt.span));
+                }
+
+                let value = match value {
+                    Some(value) => self.eval(value , env)?,
+                    None => Value::Nil,
+                };
+
+                return Ok(Flow::Return(value));
+
+            }
+
+            StmtKind::Expr(expr) => {
+                self.eval(expr , env)?;
+            }
+
+            // the else ifs are walked in a loop so a long chain doesn't recurse
+            StmtKind::If { .. } => {
+
+                let mut current = stmt;
+
+                while let StmtKind::If { condition , then_branch , else_branch } = &current.kind {
+
+                    if self.eval(condition , env)?.is_truthy() {
+                        return self.execute_block(then_branch , env);
+                    }
+
+                    match else_branch {
+                        Some(else_branch) => current = else_branch,
+                        None => return Ok(Flow::Normal),
+                    }
+
+                }
+
+                return self.execute_stmt(current , env);
+
+            }
+
+            StmtKind::While { condition , body } => {
+
+                self.loops += 1;
+                let flow = self.execute_loop(condition , body , env);
+                self.loops -= 1;
+
+                return flow;
+
+            }
+
+            StmtKind::Block(block) => return self.execute_block(block , env),
+
+            StmtKind::Break if self.loops == 0 => return Err(error(RuntimeErrorKind::BreakOutsideLoop , stmt.span)),
+            StmtKind::Break => return Ok(Flow::Break),
+
+            StmtKind::Continue if self.loops == 0 => return Err(error(RuntimeErrorKind::ContinueOutsideLoop , stmt.span)),
+            StmtKind::Continue => return Ok(Flow::Continue),
+
+        }
+
+        Ok(Flow::Normal)
+
+    }
+
+    fn execute_loop(&mut self , condition: &Expr , body: &Block , env: &Rc<Env>) -> Result<Flow , Errors> {
+
+        while self.eval(condition , env)?.is_truthy() {
+            match self.execute_block(body , env)? {
+                Flow::Break => break,
+                Flow::Return(value) => return Ok(Flow::Return(value)),
+                Flow::Normal | Flow::Continue => {}
+            }
+        }
+
+        Ok(Flow::Normal)
+
+    }
+
+    // runs the statements in a new scope inside env
+    fn execute_block(&mut self , block: &Block , env: &Rc<Env>) -> Result<Flow , Errors> {
+
+        let scope = Env::new(Some(env.clone()));
+        let flow = self.execute_stmts(&block.stmts , &scope);
+
+        self.leave(&scope);
+        flow
+
+    }
+
+    // frees the scope that is left if only its own values hold it (see Env::release)
+    // if something else holds it too it is kept to check again once that may be gone
+    fn leave(&mut self , scope: &Rc<Env>) {
+
+        if !Env::release(scope) {
+            return;
+        }
+
+        self.suspects.push(Rc::downgrade(scope));
+
+        if self.suspects.len() >= self.suspects_limit {
+            self.collect();
+        }
+
+    }
+
+    // checks the suspects again , the ones still held stay
+    fn collect(&mut self) {
+
+        for suspect in std::mem::take(&mut self.suspects) {
+            if let Some(scope) = suspect.upgrade() {
+                if Env::release(&scope) {
+                    self.suspects.push(suspect);
+                }
+            }
+        }
+
+        self.suspects_limit = SUSPECTS.max(self.suspects.len() * 2);
+
+    }
+
+    fn execute_stmts(&mut self , stmts: &[Stmt] , scope: &Rc<Env>) -> Result<Flow , Errors> {
+
+        for stmt in stmts {
+            match self.execute_stmt(stmt , scope)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+
+        Ok(Flow::Normal)
+
+    }
+
+    fn eval(&mut self , expr: &Expr , env: &Rc<Env>) -> Result<Value , Errors> {
+
+        // every call and every nested expression goes through here
+        if self.stack_start.abs_diff(stack_position()) > self.stack_budget {
+            return Err(error(RuntimeErrorKind::StackOverflow , expr.span));
+        }
+
+        match &expr.kind {
+
+            ExprKind::Literal(literal) => match literal {
+                Literal::Number(value) => Ok(Value::Int(*value)),
+                Literal::Float(value) => Ok(Value::Float(*value)),
+                // ints are 64 bits at runtime
+                Literal::BigInt(_) => Err(error(RuntimeErrorKind::IntegerOverflow , expr.span)),
+                Literal::Text(text) => Ok(Value::Str(Rc::from(text.as_str()))),
+                Literal::Bool(value) => Ok(Value::Bool(*value)),
+                Literal::Nil => Ok(Value::Nil),
+            }
+
+            ExprKind::Ident(name) => env.get(name).ok_or_else(|| {
+                error(RuntimeErrorKind::UndefinedVariable(name.clone()) , expr.span)
+            }),
+
+            ExprKind::Grouping(inner) => self.eval(inner , env),
+
+            ExprKind::Unary { op , operand } => {
+                let operand = self.eval(operand , env)?;
+                unary(*op , operand).map_err(|kind| error(kind , expr.span))
+            }
+
+            // && and || only look at the right side if they need to
+            ExprKind::Binary { op: Op::AndAnd , left , right } => {
+
+                if !self.eval(left , env)?.is_truthy() {
+                    return Ok(Value::Bool(false));
+                }
+
+                Ok(Value::Bool(self.eval(right , env)?.is_truthy()))
+
+            }
+
+            ExprKind::Binary { op: Op::OrOr , left , right } => {
+
+                if self.eval(left , env)?.is_truthy() {
+                    return Ok(Value::Bool(true));
+                }
+
+                Ok(Value::Bool(self.eval(right , env)?.is_truthy()))
+
+            }
+
+            ExprKind::Binary { op , left , right } => {
+                let left = self.eval(left , env)?;
+                let right = self.eval(right , env)?;
+                binary(*op , left , right).map_err(|kind| error(kind , expr.span))
+            }
+
+            ExprKind::Assign { name , op , value } => {
+
+                let mut value = self.eval(value , env)?;
+
+                if let Some(op) = compound_op(*op) {
+
+                    let current = env.get(name).ok_or_else(|| {
+                        error(RuntimeErrorKind::UndefinedVariable(name.clone()) , expr.span)
+                    })?;
+
+                    value = binary(op , current , value).map_err(|kind| error(kind , expr.span))?;
+
+                }
+
+                if !env.assign(name , value.clone()) {
+                    return Err(error(RuntimeErrorKind::UndefinedVariable(name.clone()) , expr.span));
+                }
+
+                Ok(value)
+
+            }
+
+            ExprKind::Call { callee , args } => {
+
+                let callee = self.eval(callee , env)?;
+                let mut values = Vec::with_capacity(args.len());
+
+                for arg in args {
+                    values.push(self.eval(arg , env)?);
+                }
+
+                self.call(callee , values , expr.span)
+
+            }
+
+        }
+
+    }
+
+    fn call(&mut self , callee: Value , args: Vec<Value> , span: Span) -> Result<Value , Errors> {
+
+        // a function value that is not stored has its scope (see Env::get)
+        let (function , closure) = match callee {
+            Value::Function(function , closure) => (function , closure.unwrap()),
+            Value::Builtin(builtin) => return builtin.call(&args , &mut self.output),
+            other => return Err(error(RuntimeErrorKind::NotCallable(other.type_name()) , span)),
+        };
+
+        if args.len() != function.params.len() {
+            let kind = RuntimeErrorKind::WrongArgumentCount { expected: function.params.len() , found: args.len() };
+            return Err(error(kind , span));
+        }
+
+        let scope = Env::new(Some(closure));
+
+        for (param , arg) in function.params.iter().zip(args) {
+            scope.define(param , arg);
+        }
+
+        // a loop around the call doesn't make a break in the function valid
+        let loops = std::mem::replace(&mut self.loops , 0);
+        self.calls += 1;
+
+        let flow = self.execute_block(&function.body , &scope);
+        self.leave(&scope);
+
+        self.calls -= 1;
+        self.loops = loops;
+
+        match flow? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Value::Nil),
+        }
+
+    }
+
+}
+
+pub(crate) fn error(kind: RuntimeErrorKind , span: Span) -> Errors {
+    Errors::RuntimeError(RuntimeError { kind , span })
+}
+
+// the operator a compound assignment like += applies before assigning
+pub(crate) fn compound_op(op: Op) -> Option<Op> {
+    match op {
+        Op::PlusAssign => Some(Op::Plus),
+        Op::MinusAssign => Some(Op::Minus),
+        Op::StarAssign => Some(Op::Star),
+        Op::SlashAssign => Some(Op::Slash),
+        Op::AmpAssign => Some(Op::Amp),
+        Op::PipeAssign => Some(Op::Pipe),
+        Op::CaretAssign => Some(Op::Caret),
+        Op::StarStarAssign => Some(Op::StarStar),
+        Op::ShlAssign => Some(Op::Shl),
+        Op::ShrAssign => Some(Op::Shr),
+        _ => None,
+    }
+}
+
+pub(crate) fn unary(op: Op , operand: Value) -> Result<Value , RuntimeErrorKind> {
+
+    match (op , operand) {
+        (Op::Bang , operand) => Ok(Value::Bool(!operand.is_truthy())),
+        (Op::Minus , Value::Int(value)) => value.checked_neg().map(Value::Int).ok_or(RuntimeErrorKind::IntegerOverflow),
+        (Op::Minus , Value::Float(value)) => Ok(Value::Float(-value)),
+        (Op::Plus , Value::Int(value)) => Ok(Value::Int(value)),
+        (Op::Plus , Value::Float(value)) => Ok(Value::Float(value)),
+        (Op::Tilde , Value::Int(value)) => Ok(Value::Int(!value)),
+        (op , operand) => Err(RuntimeErrorKind::InvalidOperand { op , operand: operand.type_name() }),
+    }
+
+}
+
+// ints stay ints unless one side is a float , integer division rounds toward zero
+pub(crate) fn binary(op: Op , left: Value , right: Value) -> Result<Value , RuntimeErrorKind> {
+
+    let invalid = RuntimeErrorKind::InvalidOperands { op , left: left.type_name() , right: right.type_name() };
+
+    match op {
+        Op::Eq => return Ok(Value::Bool(left == right)),
+        Op::Ne => return Ok(Value::Bool(left != right)),
+        _ => {}
+    }
+
+    match (left , right) {
+
+        (Value::Int(left) , Value::Int(right)) => int_binary(op , left , right).unwrap_or(Err(invalid)),
+
+        (Value::Float(left) , Value::Float(right)) => float_binary(op , left , right).ok_or(invalid),
+        (Value::Int(left) , Value::Float(right)) => float_binary(op , left as f64 , right).ok_or(invalid),
+        (Value::Float(left) , Value::Int(right)) => float_binary(op , left , right as f64).ok_or(invalid),
+
+        (Value::Str(left) , Value::Str(right)) => match op {
+            Op::Plus => Ok(Value::Str(Rc::from(format!("{}{}" , left , right)))),
+            Op::Lt => Ok(Value::Bool(left < right)),
+            Op::Gt => Ok(Value::Bool(left > right)),
+            Op::Le => Ok(Value::Bool(left <= right)),
+            Op::Ge => Ok(Value::Bool(left >= right)),
+            _ => Err(invalid),
+        }
+
+        _ => Err(invalid),
+
+    }
+
+}
+
+// returns None if the operator doesn't work on ints
+fn int_binary(op: Op , left: i64 , right: i64) -> Option<Result<Value , RuntimeErrorKind>> {
+
+    let overflow = RuntimeErrorKind::IntegerOverflow;
+
+    let value = match op {
+        Op::Plus => left.checked_add(right).ok_or(overflow),
+        Op::Minus => left.checked_sub(right).ok_or(overflow),
+        Op::Star => left.checked_mul(right).ok_or(overflow),
+        Op::Slash if right == 0 => Err(RuntimeErrorKind::DivisionByZero),
+        Op::Slash => left.checked_div(right).ok_or(overflow),
+        // a negative power can't be an int
+        Op::StarStar if right < 0 => return Some(Ok(Value::Float((left as f64).powf(right as f64)))),
+        Op::StarStar => u32::try_from(right).ok().and_then(|right| left.checked_pow(right)).ok_or(overflow),
+        Op::Amp => Ok(left & right),
+        Op::Pipe => Ok(left | right),
+        Op::Caret => Ok(left ^ right),
+        Op::Shl => u32::try_from(right).ok().and_then(|right| left.checked_shl(right)).ok_or(overflow),
+        Op::Shr => u32::try_from(right).ok().and_then(|right| left.checked_shr(right)).ok_or(overflow),
+        Op::Lt => return Some(Ok(Value::Bool(left < right))),
+        Op::Gt => return Some(Ok(Value::Bool(left > right))),
+        Op::Le => return Some(Ok(Value::Bool(left <= right))),
+        Op::Ge => return Some(Ok(Value::Bool(left >= right))),
+        _ => return None,
+    };
+
+    Some(value.map(Value::Int))
+
+}
+
+// returns None if the operator doesn't work on floats
+fn float_binary(op: Op , left: f64 , right: f64) -> Option<Value> {
+
+    let value = match op {
+        Op::Plus => Value::Float(left + right),
+        Op::Minus => Value::Float(left - right),
+        Op::Star => Value::Float(left * right),
+        Op::Slash => Value::Float(left / right),
+        Op::StarStar => Value::Float(left.powf(right)),
+        Op::Lt => Value::Bool(left < right),
+        Op::Gt => Value::Bool(left > right),
+        Op::Le => Value::Bool(left <= right),
+        Op::Ge => Value::Bool(left >= right),
+        _ => return None,
+    };
+
+    Some(value)
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    // runs the program and returns what it printed
+    fn output_of(text: &str) -> String {
+        let mut interpreter = Interpreter::with_output(vec![]);
+        interpreter.run_text(text).unwrap();
+        String::from_utf8(interpreter.output().clone()).unwrap()
+    }
+
+    fn eval(text: &str) -> Value {
+        Interpreter::with_output(vec![]).run_text(text).unwrap()
+    }
+
+    fn error_of(text: &str) -> RuntimeError {
+        match Interpreter::with_output(vec![]).run_text(text).unwrap_err() {
+            Errors::RuntimeError(error) => error,
+            error => panic!("unexpected error {:?}" , error),
+        }
+    }
+
+    #[test]
+    fn arithmetic() {
+
+        assert_eq!(eval("1 + 2 * 3") , Value::Int(7));
+        assert_eq!(eval("7 / 2") , Value::Int(3));
+        assert_eq!(eval("-7 / 2") , Value::Int(-3));
+        assert_eq!(eval("7 / 2.0") , Value::Float(3.5));
+        assert_eq!(eval("2 ** 10") , Value::Int(1024));
+        assert_eq!(eval("2 ** -1") , Value::Float(0.5));
+        assert_eq!(eval("1 << 4 | 1") , Value::Int(17));
+        assert_eq!(eval("~0") , Value::Int(-1));
+        assert_eq!(eval("\"ab\" + \"c\"") , Value::Str(Rc::from("abc")));
+
+        assert_eq!(eval("1 < 2 && 2.5 >= 2") , Value::Bool(true));
+        assert_eq!(eval("1 == 1.0") , Value::Bool(true));
+        assert_eq!(eval("\"a\" < \"b\"") , Value::Bool(true));
+        assert_eq!(eval("nil == false") , Value::Bool(false));
+        assert_eq!(eval("!nil") , Value::Bool(true));
+
+    }
+
+    #[test]
+    fn variables_and_scopes() {
+
+        assert_eq!(output_of("
+            let x = 1
+            let y
+            {
+                let x = 2
+                x += 10
+                y = x
+                print(x)
+            }
+            print(x , y)
+        ") , "12\n1 12\n");
+
+    }
+
+    #[test]
+    fn control_flow() {
+
+        assert_eq!(output_of("
+            let i = 0
+            let total = 0
+            while true {
+                i += 1
+                if i > 10 { break }
+                else if i / 2 * 2 == i { continue }
+                total += i
+            }
+            print(total , i)
+        ") , "25 11\n");
+
+    }
+
+    #[test]
+    fn functions_and_closures() {
+
+        assert_eq!(output_of("
+            fn fib(n) {
+                if n < 2 { return n }
+                return fib(n - 1) + fib(n - 2)
+            }
+
+            fn counter() {
+                let count = 0
+                fn next() { count += 1 return count }
+                return next
+            }
+
+            let next = counter()
+            next()
+            print(fib(15) , next() , counter()())
+            print(print)
+        ") , "610 2 1\n<builtin print>\n");
+
+    }
+
+    #[test]
+    fn functions_are_freed() {
+
+        let function = |value: Value| match value {
+            Value::Function(function , _) => Rc::downgrade(&function),
+            value => panic!("{:?} is not a function" , value),
+        };
+
+        let mut interpreter = Interpreter::with_output(vec![]);
+
+        // a function declared in a call is freed with the call unless it is returned
+        let inner = function(interpreter.run_text("fn make() { fn inner() { return 1 } return inner } make()").unwrap());
+        assert!(inner.upgrade().is_none());
+
+        let a = function(interpreter.run_text("fn counter() { let count = 0 fn next() { count += 1 return count } return next } let a = counter() a").unwrap());
+        let b = function(interpreter.run_text("fn b() {} let c = b b").unwrap());
+        let d = function(interpreter.run_text("let d = counter() d").unwrap());
+
+        assert_eq!(interpreter.run_text("a() + a()").unwrap() , Value::Int(3));
+        assert!(interpreter.run_text("a = nil").is_ok());
+        assert!(a.upgrade().is_none());
+
+        // a function stored in a scope around its own scope is freed with them , t counts s while they live
+        let text = interpreter.run_text("
+            let s = \"text\"
+            fn outer() { let h = nil if true { let t = s fn inner() {} h = inner } }
+            outer()
+            s
+        ").unwrap();
+
+        match text {
+            Value::Str(text) => assert_eq!(Rc::strong_count(&text) , 2),
+            value => panic!("{:?} is not a string" , value),
+        }
+
+        // and once nothing else holds them if it was returned
+        let kept = function(interpreter.run_text("fn keep() { let h = nil if true { fn inner() { return h } h = inner } return h } let k = keep() k").unwrap());
+        assert_eq!(interpreter.run_text("k() == k").unwrap() , Value::Bool(true));
+        assert!(kept.upgrade().is_some());
+        assert!(interpreter.run_text("k = nil").is_ok());
+        assert!(kept.upgrade().is_none());
+
+        // functions and closures stored in globals go with the interpreter
+        assert!(b.upgrade().is_some() && d.upgrade().is_some());
+        drop(interpreter);
+        assert!(b.upgrade().is_none() && d.upgrade().is_none());
+
+    }
+
+    #[test]
+    fn runtime_errors() {
+
+        let error = error_of("let x = 1\nx + \"a\"");
+        assert_eq!(error.kind , RuntimeErrorKind::InvalidOperands { op: Op::Plus , left: "int" , right: "string" });
+        assert_eq!((error.span.start.line , error.span.start.column) , (2 , 1));
+
+        assert_eq!(error_of("y = 1").kind , RuntimeErrorKind::UndefinedVariable(String::from("y")));
+        assert_eq!(error_of("1 / 0").kind , RuntimeErrorKind::DivisionByZero);
+        assert_eq!(error_of("9223372036854775807 + 1").kind , RuntimeErrorKind::IntegerOverflow);
+        assert_eq!(error_of("-\"a\"").kind , RuntimeErrorKind::InvalidOperand { op: Op::Minus , operand: "string" });
+        assert_eq!(error_of("1()").kind , RuntimeErrorKind::NotCallable("int"));
+        assert_eq!(error_of("fn f(a) {} f()").kind , RuntimeErrorKind::WrongArgumentCount { expected: 1 , found: 0 });
+        assert_eq!(error_of("break").kind , RuntimeErrorKind::BreakOutsideLoop);
+        assert_eq!(error_of("while true { fn f() { continue } f() }").kind , RuntimeErrorKind::ContinueOutsideLoop);
+        assert_eq!(error_of("return 1").kind , RuntimeErrorKind::ReturnOutsideFunction);
+
+        // the program is stopped before it runs out of the 2MiB stack of a spawned thread
+        let thread = std::thread::Builder::new().stack_size(2 << 20).spawn(|| {
+            [
+                error_of("fn f() { f() } f()").kind,
+                error_of("fn g(n) { if true { return 1 + (2 * (3 - (4 + g(n)))) } } g(0)").kind,
+            ]
+        });
+
+        assert_eq!(thread.unwrap().join().unwrap() , [RuntimeErrorKind::StackOverflow , RuntimeErrorKind::StackOverflow]);
+
+        // a smaller stack needs a smaller budget
+        let thread = std::thread::Builder::new().stack_size(512 << 10).spawn(|| {
+            let mut interpreter = Interpreter::with_output(vec![]).with_stack_budget(256 << 10);
+            interpreter.run_text("fn f(n) { if n == 0 { return 0 } return 1 + f(n - 1) } f(100000)").unwrap_err()
+        });
+
+        match thread.unwrap().join().unwrap() {
+            Errors::RuntimeError(error) => assert_eq!(error.kind , RuntimeErrorKind::StackOverflow),
+            error => panic!("unexpected error {:?}" , error),
+        }
+
+    }
+
+}
@@ -1,79 +1,393 @@
-use crate::{statemachine::{Token, Machine}, common::Errors};
+use std::{collections::VecDeque , io::BufRead};
+
+use crate::{
+    statemachine::{SpannedToken , Token , Machine},
+    common::{Errors , Position , Span},
+    source::{Source , TextSource , ChunkSource , ReaderSource},
+};
 
 // you can either get one token at a time from the Lexer
 // or you can get a list of all tokens
+// get_new_token returns None once the input has ended
+// lex stops at the first error while lex_recovering reports every error it finds
+// and puts a Token::Error in place of the text it could not lex
 pub trait Lexer {
-    fn get_new_token(&mut self) -> Result<(Token , bool) , Errors>;
-    fn lex(&mut self) -> Result<Vec<Token> , Errors>;
+    fn get_new_token(&mut self) -> Result<Option<SpannedToken> , Errors>;
+    fn lex(&mut self) -> Result<Vec<SpannedToken> , Errors>;
+    fn lex_recovering(&mut self) -> (Vec<SpannedToken> , Vec<Errors>);
+}
+
+// the lexer resyncs after an error at whitespace or at one of these characters
+fn is_delimiter(input: char) -> bool {
+    input.is_whitespace() || ['(' , ')' , '{' , '}' , '[' , ']' , ',' , ';'].contains(&input)
 }
 
-// text shows the source text
-// pos shows the current character in the source text
+// source is where the source text is read from (see source::Source)
+// location is the line , column and byte offset of the current character
 // machine is an implementation of the Machine trait
-pub struct ImagineLexer<T: Machine> {
-    text: String,
-    pos: usize,
+// finished is set once the input has ended (or can't be read anymore)
+// emit_eof asks for a Token::Eof after the last token and eof_emitted remembers it was sent
+// unread holds the characters the machine gave back (see Machine::unread) , they are fed again
+// before the rest of the source
+// fed holds the characters fed to the machine since the last token it emitted
+// so an error token has the source text and not what the machine made of it like "\q" for \q
+
+// the source text is read as the tokens are asked for
+// so a lexer made with from_reader or from_chunks can lex input that is still arriving
+pub struct ImagineLexer<T: Machine , S: Source = TextSource> {
+    source: S,
+    location: Position,
     machine: T,
+    finished: bool,
+    emit_eof: bool,
+    eof_emitted: bool,
+    unread: VecDeque<(char , Position)>,
+    fed: VecDeque<(char , Position)>,
 }
 
 impl<T: Machine> ImagineLexer<T> {
     pub fn new(text: String , machine: T) -> ImagineLexer<T> {
-        ImagineLexer {text , pos: 0 , machine }
+        ImagineLexer::from_source(TextSource::new(text) , machine)
     }
 }
 
-impl<T: Machine> Lexer for ImagineLexer<T> {
+impl<T: Machine , R: BufRead> ImagineLexer<T , ReaderSource<R>> {
+    pub fn from_reader(reader: R , machine: T) -> ImagineLexer<T , ReaderSource<R>> {
+        ImagineLexer::from_source(ReaderSource::new(reader) , machine)
+    }
+}
 
-    fn get_new_token(&mut self) -> Result<(Token , bool) , Errors> {
+impl<T: Machine , I: Iterator> ImagineLexer<T , ChunkSource<I>> where I::Item: AsRef<str> {
+    pub fn from_chunks<C>(chunks: C , machine: T) -> ImagineLexer<T , ChunkSource<I>>
+    where C: IntoIterator<IntoIter = I> {
+        ImagineLexer::from_source(ChunkSource::new(chunks.into_iter()) , machine)
+    }
+}
 
-        while self.pos < self.text.len() {
+impl<T: Machine , S: Source> ImagineLexer<T , S> {
 
-            let input = self.text.chars()
-            .nth(self.pos)
-            .unwrap();
+    pub fn from_source(source: S , machine: T) -> ImagineLexer<T , S> {
+        ImagineLexer {
+            source,
+            location: Position::default(),
+            machine,
+            finished: false,
+            emit_eof: false,
+            eof_emitted: false,
+            unread: VecDeque::new(),
+            fed: VecDeque::new(),
+        }
+    }
+
+    // ends the tokens with a Token::Eof , handy for parsers that want a token to look at the end
+    pub fn emit_eof(mut self , emit_eof: bool) -> ImagineLexer<T , S> {
+        self.emit_eof = emit_eof;
+        self
+    }
+
+    // returns the current character with its position and moves past it
+    // a source that can't be read ends the input
+    fn next_char(&mut self) -> Result<Option<(char , Position)> , Errors> {
+
+        // location stays right after the last character handed out so the tokens end there
+        if let Some((input , location)) = self.unread.pop_front() {
+            self.location = location.advance(input);
+            return Ok(Some((input , location)));
+        }
+
+        let input = match self.source.next_char() {
+            Ok(Some(input)) => input,
+            Ok(None) => return Ok(None),
+            Err(error) => {
+                self.finished = true;
+                return Err(error);
+            }
+        };
+
+        let location = self.location;
+        self.location = location.advance(input);
+
+        Ok(Some((input , location)))
+
+    }
+
+    fn peek_char(&mut self) -> Result<Option<char> , Errors> {
+        match self.unread.front() {
+            Some((input , _)) => Ok(Some(*input)),
+            None => self.source.peek_char(),
+        }
+    }
+
+    // the characters the machine gave back come before the ones still in the queue
+    fn take_unread(&mut self) {
+        for (input , location) in self.machine.unread().into_iter().rev() {
+            self.push_back(input , location);
+        }
+    }
+
+    // the character is read again , it is fed again too so it is not kept in fed
+    fn push_back(&mut self , input: char , location: Position) {
+        self.fed.retain(|&(_ , fed)| fed.offset < location.offset);
+        self.unread.push_front((input , location));
+    }
 
-            self.pos += 1;
+    // the characters fed to the machine from start on as they are in the source
+    fn fed_text(&mut self , start: Position) -> String {
 
-            let token = self.machine.feed(input)?;
+        let text = self.fed.iter().filter(|(_ , location)| location.offset >= start.offset).map(|&(input , _)| input).collect();
+        self.fed.clear();
+
+        text
+
+    }
+
+    // a token was emitted , the characters before its end are not needed anymore
+    fn emitted(&mut self , token: Option<SpannedToken>) -> Option<SpannedToken> {
+
+        if let Some(token) = &token {
+            while self.fed.front().is_some_and(|(_ , location)| location.offset < token.span.end.offset) {
+                self.fed.pop_front();
+            }
+        }
+
+        token
+
+    }
+
+    // if errors is given the error is recorded there and an error token is returned instead
+    fn next_token(&mut self , mut errors: Option<&mut Vec<Errors>>) -> Result<Option<SpannedToken> , Errors> {
+
+        while !self.finished {
+
+            let (input , location) = match self.next_char()? {
+                Some(next) => next,
+                None => {
+
+                    // a machine that read past its last token emits it now and gives back the rest
+                    let token = match self.machine.flush() {
+                        Ok(token) => token,
+                        Err(error) => Some(self.end_error(error , errors.as_deref_mut())?),
+                    };
+
+                    self.take_unread();
+
+                    if token.is_some() {
+                        return Ok(self.emitted(token));
+                    }
+
+                    if !self.unread.is_empty() {
+                        continue;
+                    }
+
+                    self.finished = true;
+                    return self.final_token(errors);
+
+                }
+            };
 
             // feed the input to the machine until a token is returned
-            if let Some(token) = token {
-                return Ok((token , false));
+            self.fed.push_back((input , location));
+            let token = self.machine.feed(input , location);
+            self.take_unread();
+
+            match token {
+                Ok(Some(token)) => return Ok(self.emitted(Some(token))),
+                Ok(None) => {}
+                Err(error) => match errors.as_mut() {
+                    Some(errors) => {
+
+                        // the input is read again and fails on its own , a hidden comment leaves no token
+                        match self.keep_finished(input , location) {
+                            Some(token) if token.token == Token::Blank => continue,
+                            Some(token) => return Ok(Some(token)),
+                            None => {}
+                        }
+
+                        errors.push(error);
+                        return Ok(Some(self.recover(input , location)?));
+
+                    }
+                    None => return Err(error),
+                }
             }
 
+        }
+
+        Ok(self.eof_token())
+
+    }
+
+    // the token the machine was still building when the input ended
+    // the blank token a machine returns when there is nothing left is dropped
+    fn final_token(&mut self , errors: Option<&mut Vec<Errors>>) -> Result<Option<SpannedToken> , Errors> {
 
+        let token = match self.machine.get_final_token(self.location) {
+            Ok(token) => token,
+            Err(error) => self.end_error(error , errors)?,
+        };
+
+        if token.token == Token::Blank {
+            return Ok(self.eof_token());
         }
 
-        Ok((self.machine.get_final_token() , true))
+        Ok(Some(token))
 
     }
 
-    fn lex(&mut self) -> Result<Vec<Token> , Errors> {
-        
-        let (mut token , mut is_last_token) = self.get_new_token()?;
-        let mut tokens = vec![];
+    // an error at the end of the input , what the machine was building becomes an error token
+    fn end_error(&mut self , error: Errors , errors: Option<&mut Vec<Errors>>) -> Result<SpannedToken , Errors> {
 
-        while !is_last_token  {
+        let errors = match errors {
+            Some(errors) => errors,
+            None => return Err(error),
+        };
 
-            tokens.push(token);
-            (token , is_last_token) = self.get_new_token()?;
+        errors.push(error);
+        let start = self.machine.reset().map_or(self.location , |(_ , start)| start);
+        let text = self.fed_text(start);
+
+        Ok(SpannedToken { token: Token::Error(text) , span: Span::new(start , self.location) })
+
+    }
+
+    fn eof_token(&mut self) -> Option<SpannedToken> {
+
+        if !self.emit_eof || self.eof_emitted {
+            return None;
+        }
+
+        self.eof_emitted = true;
+        Some(SpannedToken { token: Token::Eof , span: Span::new(self.location , self.location) })
+
+    }
+
+    // the token the machine was building if it is complete and the input that failed can't start one
+    // the input goes back to be read again with the machine in its starting state
+    fn keep_finished(&mut self , input: char , location: Position) -> Option<SpannedToken> {
+
+        if self.machine.can_start(input) {
+            return None;
+        }
+
+        let token = self.machine.get_final_token(location).ok()?;
+
+        // nothing was being built so the input failed on its own already
+        self.machine.reset()?;
+        self.push_back(input , location);
+
+        self.emitted(Some(token))
+
+    }
+
+    // drops the token the machine was building and skips ahead to the next delimiter
+    // everything dropped or skipped becomes a single error token
+    fn recover(&mut self , input: char , location: Position) -> Result<SpannedToken , Errors> {
+
+        let dropped = self.machine.reset();
+        let start = dropped.as_ref().map_or(location , |(_ , start)| *start);
+
+        self.fed.retain(|&(_ , fed)| fed.offset < location.offset);
+        let mut text = self.fed_text(start);
+
+        // the delimiter is read again with the machine back in its starting state
+        // and the error token ends right before it , unless the delimiter failed on its own
+        if dropped.is_some() && is_delimiter(input) {
+            self.push_back(input , location);
+            return Ok(SpannedToken { token: Token::Error(text) , span: Span::new(start , location) });
+        }
+
+        text.push(input);
+
+        while let Some(input) = self.peek_char()? {
+
+            if is_delimiter(input) {
+                break;
+            }
+
+            self.next_char()?;
+            text.push(input);
 
         }
 
-        tokens.push(token);
+        Ok(SpannedToken { token: Token::Error(text) , span: Span::new(start , self.location) })
+
+    }
+
+}
+
+impl<T: Machine , S: Source> Lexer for ImagineLexer<T , S> {
+
+    fn get_new_token(&mut self) -> Result<Option<SpannedToken> , Errors> {
+        self.next_token(None)
+    }
+
+    fn lex(&mut self) -> Result<Vec<SpannedToken> , Errors> {
+
+        let mut tokens = vec![];
+
+        while let Some(token) = self.get_new_token()? {
+            tokens.push(token);
+        }
 
         Ok(tokens)
 
     }
 
+    fn lex_recovering(&mut self) -> (Vec<SpannedToken> , Vec<Errors>) {
+
+        let mut errors = vec![];
+        let mut tokens = vec![];
+
+        loop {
+
+            // with somewhere to record errors next_token only fails if the source can't be read
+            // and then there is nothing left to lex
+            match self.next_token(Some(&mut errors)) {
+                Ok(Some(token)) => tokens.push(token),
+                Ok(None) => break,
+                Err(error) => {
+                    errors.push(error);
+                    break;
+                }
+            }
+
+        }
+
+        (tokens , errors)
+
+    }
+
+}
+
+// the lexer can be used like any other iterator , it ends with the input
+// after an error it skips to the next delimiter and carries on from there like lex_recovering
+// the error takes the place of the error token
+impl<T: Machine , S: Source> Iterator for ImagineLexer<T , S> {
+
+    type Item = Result<SpannedToken , Errors>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+
+        let mut errors = vec![];
+        let token = self.next_token(Some(&mut errors));
+
+        match errors.pop() {
+            Some(error) => Some(Err(error)),
+            None => token.transpose(),
+        }
+
+    }
+
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
 
     use crate::{
     lexer::*,
-    statemachine::{Token , ImagineMachine}
+    statemachine::{Token , ImagineMachine , Keyword , Op},
+    common::{LexErrorKind , NumberError}
     };
 
     // this test both covers the get_next_token the lex method
@@ -84,27 +398,32 @@ mod tests {
         let mut lexer = ImagineLexer::new(String::from("72 3.14 player if
         player2 36 100 -7 + 8 var += 12 !bool !(2 + 2) block{code}") , machine);
 
-        assert_eq!(lexer.lex().unwrap() , vec![
+        let tokens: Vec<Token> = lexer.lex().unwrap()
+        .into_iter()
+        .map(|token| token.token)
+        .collect();
+
+        assert_eq!(tokens , vec![
             Token::Number(72),
             Token::Float(3.14),
             Token::Ident("player".to_string()),
-            Token::Keyword("if".to_string()),
+            Token::Keyword(Keyword::If),
             Token::Ident("player2".to_string()),
             Token::Number(36),
             Token::Number(100),
-            Token::Operator("-".to_string()),
+            Token::Operator(Op::Minus),
             Token::Number(7),
-            Token::Operator(String::from("+")),
+            Token::Operator(Op::Plus),
             Token::Number(8),
             Token::Ident("var".to_string()),
-            Token::Operator("+=".to_string()),
+            Token::Operator(Op::PlusAssign),
             Token::Number(12),
-            Token::Operator("!".to_string()),
+            Token::Operator(Op::Bang),
             Token::Ident("bool".to_string()),
-            Token::Operator("!".to_string()),
+            Token::Operator(Op::Bang),
             Token::LPAR,
             Token::Number(2),
-            Token::Operator("+".to_string()),
+            Token::Operator(Op::Plus),
             Token::Number(2),
             Token::RPAR,
             Token::Ident("block".to_string()),
@@ -115,4 +434,235 @@ mod tests {
 
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn span_test() {
+
+        let machine = ImagineMachine::new();
+        let mut lexer = ImagineLexer::new(String::from("if x\n  += 10") , machine);
+        let tokens = lexer.lex().unwrap();
+
+        let at = |offset , line , column| Position { offset , line , column };
+        let spans: Vec<Span> = tokens.iter().map(|token| token.span).collect();
+
+        assert_eq!(spans , vec![
+            Span::new(at(0 , 1 , 1) , at(2 , 1 , 3)),
+            Span::new(at(3 , 1 , 4) , at(4 , 1 , 5)),
+            Span::new(at(7 , 2 , 3) , at(9 , 2 , 5)),
+            Span::new(at(10 , 2 , 6) , at(12 , 2 , 8)),
+        ]);
+
+    }
+
+    #[test]
+    fn recovering_test() {
+
+        let machine = ImagineMachine::new();
+        let mut lexer = ImagineLexer::new(String::from("12ab + x @@ y 1e)") , machine);
+        let (tokens , errors) = lexer.lex_recovering();
+
+        let tokens: Vec<Token> = tokens.into_iter().map(|token| token.token).collect();
+
+        assert_eq!(tokens , vec![
+            Token::Error("12ab".to_string()),
+            Token::Operator(Op::Plus),
+            Token::Ident("x".to_string()),
+            Token::Error("@@".to_string()),
+            Token::Ident("y".to_string()),
+            Token::Error("1e".to_string()),
+            Token::RPAR,
+        ]);
+
+        let kinds: Vec<LexErrorKind> = errors.into_iter().map(|error| match error {
+            Errors::SyntaxError(error) => error.kind,
+            error => panic!("unexpected error {:?}" , error),
+        }).collect();
+
+        assert_eq!(kinds , vec![
+            LexErrorKind::MalformedNumber(NumberError::InvalidSuffix),
+            LexErrorKind::UnexpectedCharacter,
+            LexErrorKind::MalformedNumber(NumberError::MissingExponent),
+        ]);
+
+    }
+
+    #[test]
+    fn recovering_keeps_source_text() {
+
+        // the error token is the text as it was written , not the string the machine decoded
+        let tokens = |text: &str| -> Vec<Token> {
+            ImagineLexer::new(String::from(text) , ImagineMachine::new()).lex_recovering().0.into_iter().map(|token| token.token).collect()
+        };
+
+        assert_eq!(tokens("\"abc\\q\" z") , vec![Token::Error("\"abc\\q\"".to_string()) , Token::Ident("z".to_string())]);
+        assert_eq!(tokens("\"\\u{41}\\q\"(") , vec![Token::Error("\"\\u{41}\\q\"".to_string()) , Token::LPAR]);
+        assert_eq!(tokens("x = \"a\\n\\") , vec![
+            Token::Ident("x".to_string()),
+            Token::Operator(Op::Assign),
+            Token::Error("\"a\\n\\".to_string()),
+        ]);
+
+    }
+
+    #[test]
+    fn recovering_keeps_finished_tokens() {
+
+        let mut lexer = ImagineLexer::new(String::from("x@ 12@y /* c */@ 1.2.3") , ImagineMachine::new());
+        let (tokens , errors) = lexer.lex_recovering();
+
+        let tokens: Vec<(Token , usize , usize)> = tokens.into_iter().map(|token| (token.token , token.span.start.offset , token.span.end.offset)).collect();
+
+        assert_eq!(tokens , vec![
+            (Token::Ident("x".to_string()) , 0 , 1),
+            (Token::Error("@".to_string()) , 1 , 2),
+            (Token::Number(12) , 3 , 5),
+            (Token::Error("@y".to_string()) , 5 , 7),
+            (Token::Error("@".to_string()) , 15 , 16),
+            // the . could start a token so it is the number that went wrong
+            (Token::Error("1.2.3".to_string()) , 17 , 22),
+        ]);
+
+        // the character is reported on its own
+        let states: Vec<String> = errors.into_iter().map(|error| match error {
+            Errors::SyntaxError(error) => error.state,
+            error => panic!("unexpected error {:?}" , error),
+        }).collect();
+
+        assert_eq!(states , ["Blank" , "Blank" , "Blank" , "Float"]);
+
+    }
+
+    #[test]
+    fn iterator_carries_on_after_errors() {
+
+        // the tokens with the errors as None
+        let items = |text: &str| -> Vec<Option<Token>> {
+            ImagineLexer::new(String::from(text) , ImagineMachine::new()).map(|item| item.ok().map(|token| token.token)).collect()
+        };
+
+        assert_eq!(items("12ab x") , vec![None , Some(Token::Ident("x".to_string()))]);
+        assert_eq!(items("1e) y") , vec![None , Some(Token::RPAR) , Some(Token::Ident("y".to_string()))]);
+        assert_eq!(items("0x) 1") , vec![None , Some(Token::RPAR) , Some(Token::Number(1))]);
+        assert_eq!(items("x@ 1") , vec![Some(Token::Ident("x".to_string())) , None , Some(Token::Number(1))]);
+
+    }
+
+    #[test]
+    fn recovering_error_span() {
+
+        let machine = ImagineMachine::new();
+        let mut lexer = ImagineLexer::new(String::from("a 12ab b") , machine);
+        let (tokens , _) = lexer.lex_recovering();
+
+        assert_eq!(tokens[1].token , Token::Error("12ab".to_string()));
+        assert_eq!(tokens[1].span.start.offset , 2);
+        assert_eq!(tokens[1].span.end.offset , 6);
+
+    }
+
+    #[test]
+    fn unicode_test() {
+
+        let machine = ImagineMachine::new();
+        let mut lexer = ImagineLexer::new(String::from("名前 = \"héllo\" + ñ") , machine);
+        let tokens = lexer.lex().unwrap();
+
+        assert_eq!(tokens[0].token , Token::Ident("名前".to_string()));
+        assert_eq!(tokens[2].token , Token::Text("héllo".to_string()));
+        assert_eq!(tokens[4].token , Token::Ident("ñ".to_string()));
+
+        let at = |offset , line , column| Position { offset , line , column };
+
+        assert_eq!(tokens[2].span , Span::new(at(9 , 1 , 6) , at(17 , 1 , 13)));
+        assert_eq!(tokens[4].span , Span::new(at(20 , 1 , 16) , at(22 , 1 , 17)));
+
+    }
+
+    #[test]
+    fn streaming_test() {
+
+        let text = "名前 += \"héllo\" // done\n(12)";
+        let expected = ImagineLexer::new(String::from(text) , ImagineMachine::new()).lex().unwrap();
+
+        // every split point , including ones inside a multi byte character for the reader
+        for split in 0..text.len() {
+
+            let bytes = text.as_bytes();
+            let reader = std::io::Read::chain(&bytes[..split] , &bytes[split..]);
+            let reader = std::io::BufReader::with_capacity(3 , reader);
+
+            let mut lexer = ImagineLexer::from_reader(reader , ImagineMachine::new());
+            assert_eq!(lexer.lex().unwrap() , expected);
+
+            if text.is_char_boundary(split) {
+                let mut lexer = ImagineLexer::from_chunks([&text[..split] , &text[split..]] , ImagineMachine::new());
+                assert_eq!(lexer.lex().unwrap() , expected);
+            }
+
+        }
+
+    }
+
+    #[test]
+    fn streaming_is_lazy() {
+
+        // the chunks after the first token must not be read yet
+        let mut read = 0;
+        let chunks = ["let " , "x " , "= " , "1"].into_iter().inspect(|_| read += 1);
+        let mut lexer = ImagineLexer::from_chunks(chunks , ImagineMachine::new());
+
+        let token = lexer.get_new_token().unwrap().unwrap();
+        assert_eq!(token.token , Token::Keyword(Keyword::Let));
+
+        drop(lexer);
+        assert_eq!(read , 1);
+
+    }
+
+    #[test]
+    fn end_of_input_test() {
+
+        let lexer = ImagineLexer::new(String::from("a b  \n ") , ImagineMachine::new());
+        let tokens: Vec<Token> = lexer.map(|token| token.unwrap().token).collect();
+
+        assert_eq!(tokens , vec![Token::Ident("a".to_string()) , Token::Ident("b".to_string())]);
+
+        let mut lexer = ImagineLexer::new(String::new() , ImagineMachine::new());
+        assert!(lexer.get_new_token().unwrap().is_none());
+        assert!(lexer.get_new_token().unwrap().is_none());
+
+    }
+
+    #[test]
+    fn eof_token_test() {
+
+        let mut lexer = ImagineLexer::new(String::from("a 1 ") , ImagineMachine::new()).emit_eof(true);
+        let tokens = lexer.lex().unwrap();
+
+        assert_eq!(tokens.len() , 3);
+        assert_eq!(tokens[2].token , Token::Eof);
+        assert_eq!(tokens[2].span.start.offset , 4);
+        assert!(lexer.next().is_none());
+
+        let tokens = ImagineLexer::new(String::from("x") , ImagineMachine::new()).emit_eof(true).lex().unwrap();
+        assert_eq!(tokens[1].token , Token::Eof);
+
+    }
+
+    #[test]
+    fn iterator_test() {
+
+        let mut lexer = ImagineLexer::new(String::from("f ( 1 @ 2 )") , ImagineMachine::new()).peekable();
+
+        assert_eq!(lexer.next().unwrap().unwrap().token , Token::Ident("f".to_string()));
+        assert_eq!(lexer.peek().unwrap().as_ref().unwrap().token , Token::LPAR);
+
+        let rest: Vec<Result<Token , Errors>> = lexer.map(|token| token.map(|token| token.token)).collect();
+
+        assert_eq!(rest[0] , Ok(Token::LPAR));
+        assert_eq!(rest[1] , Ok(Token::Number(1)));
+        assert!(rest[2].is_err());
+        assert_eq!(rest[3..] , [Ok(Token::Number(2)) , Ok(Token::RPAR)]);
+
+    }
+
+}
@@ -1,6 +1,16 @@
+pub mod bigint;
 pub mod common;
 pub mod statemachine;
 pub mod lexer;
+pub mod source;
+pub mod parser;
+pub mod interpreter;
+pub mod bytecode;
+pub mod compiler;
+pub mod vm;
+pub mod graphviz;
+pub mod dfa;
+pub mod regex;
 
 pub fn add(left: usize, right: usize) -> usize {
     left + right
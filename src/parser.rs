@@ -0,0 +1,816 @@
+use crate::{
+    bigint::BigInt,
+    statemachine::{Keyword , Op , SpannedToken , Token},
+    common::{Errors , ParseError , ParseErrorKind , Position , Span},
+};
+
+// the values that can be written directly in the source text
+#[derive(Debug , Clone , PartialEq)]
+pub enum Literal {
+    Number(i64),
+    Float(f64),
+    BigInt(BigInt),
+    Text(String),
+    Bool(bool),
+    Nil,
+}
+
+// an expression and the part of the source text it came from
+#[derive(Debug , Clone , PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+// Grouping is an expression in parentheses , it is kept so tools can print the source back
+// Assign covers = and the compound assignments like += , op says which one it is
+#[derive(Debug , Clone , PartialEq)]
+pub enum ExprKind {
+    Literal(Literal),
+    Ident(String),
+    Unary { op: Op , operand: Box<Expr> },
+    Binary { op: Op , left: Box<Expr> , right: Box<Expr> },
+    Assign { name: String , op: Op , value: Box<Expr> },
+    Grouping(Box<Expr>),
+    Call { callee: Box<Expr> , args: Vec<Expr> },
+}
+
+// a statement and the part of the source text it came from
+#[derive(Debug , Clone , PartialEq)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+// Let declares a variable , the value is optional like in let x
+// assignments are expressions (see ExprKind::Assign) so they are Expr statements
+// the else branch of an If is either another If (for else if) or a Block
+// Fn declares a function , it is called with a Call expression like any other value
+// a Return without a value is a return from a function that has nothing to give back
+#[derive(Debug , Clone , PartialEq)]
+pub enum StmtKind {
+    Let { name: String , value: Option<Expr> },
+    Fn { name: String , params: Vec<String> , body: Block },
+    Return(Option<Expr>),
+    Expr(Expr),
+    If { condition: Expr , then_branch: Block , else_branch: Option<Box<Stmt>> },
+    While { condition: Expr , body: Block },
+    Block(Block),
+    Break,
+    Continue,
+}
+
+// the statements between { and }
+#[derive(Debug , Clone , PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+// every statement of a source file
+#[derive(Debug , Clone , PartialEq)]
+pub struct Program {
+    pub stmts: Vec<Stmt>,
+}
+
+// the precedence of the operators from the loosest to the tightest
+// an operator with a higher precedence takes its operands first so 1 + 2 * 3 is 1 + (2 * 3)
+
+//  1  = += -= *= /= &= |= ^= **= <<= >>=   right associative , a = b = c is a = (b = c)
+//  2  ||                                   left associative , like the rest unless said otherwise
+//  3  &&
+//  4  |
+//  5  ^
+//  6  &
+//  7  == !=
+//  8  < > <= >=
+//  9  << >>
+// 10  + -
+// 11  * /
+// 12  prefix - + ! ~
+// 13  **                                   right associative , -2 ** 2 is -(2 ** 2)
+// 14  calls f(x)
+
+const ASSIGNMENT: u8 = 1;
+const PREFIX: u8 = 12;
+const CALL: u8 = 14;
+
+// how deep statements and expressions can be nested , every level is a recursive call
+// here and in whatever walks the tree later so ((((1)))) a hundred thousand deep is an error
+// instead of a stack overflow , 64 keeps the parser , the compiler and the interpreter
+// within a 2MiB thread stack in a debug build
+pub const MAX_DEPTH: usize = 64;
+
+// the precedence of a binary operator and whether it is right associative
+// returns None for the operators that can only be prefix ones
+fn infix_precedence(op: Op) -> Option<(u8 , bool)> {
+    match op {
+        Op::Assign
+        | Op::PlusAssign
+        | Op::MinusAssign
+        | Op::StarAssign
+        | Op::SlashAssign
+        | Op::AmpAssign
+        | Op::PipeAssign
+        | Op::CaretAssign
+        | Op::StarStarAssign
+        | Op::ShlAssign
+        | Op::ShrAssign => Some((ASSIGNMENT , true)),
+        Op::OrOr => Some((2 , false)),
+        Op::AndAnd => Some((3 , false)),
+        Op::Pipe => Some((4 , false)),
+        Op::Caret => Some((5 , false)),
+        Op::Amp => Some((6 , false)),
+        Op::Eq | Op::Ne => Some((7 , false)),
+        Op::Lt | Op::Gt | Op::Le | Op::Ge => Some((8 , false)),
+        Op::Shl | Op::Shr => Some((9 , false)),
+        Op::Plus | Op::Minus => Some((10 , false)),
+        Op::Star | Op::Slash => Some((11 , false)),
+        Op::StarStar => Some((13 , true)),
+        Op::Bang | Op::Tilde => None,
+    }
+}
+
+fn is_prefix(op: Op) -> bool {
+    matches!(op , Op::Minus | Op::Plus | Op::Bang | Op::Tilde)
+}
+
+// a pratt parser over the tokens of the lexer
+// statements are parsed by recursive descent and the expressions in them with precedence climbing
+// a ; after a statement is optional , a statement simply ends where the next one can't continue it
+// so 'x = 1 y = 2' is two statements but 'f\n(x)' is a call like in javascript
+// tokens are the tokens left to parse , comments and Token::Eof are dropped up front
+// current is the index of the next token and end is where the source text ended
+// depth is how many statements and expressions the one being parsed is in
+pub struct Parser {
+    tokens: Vec<SpannedToken>,
+    current: usize,
+    end: Position,
+    depth: usize,
+}
+
+impl Parser {
+
+    pub fn new(tokens: Vec<SpannedToken>) -> Parser {
+
+        let end = tokens.last().map(|token| token.span.end).unwrap_or_default();
+
+        let tokens = tokens.into_iter()
+            .filter(|token| !matches!(token.token , Token::Comment(_) | Token::Eof | Token::Blank))
+            .collect();
+
+        Parser { tokens , current: 0 , end , depth: 0 }
+
+    }
+
+    // parses statements until the tokens run out
+    pub fn parse_program(&mut self) -> Result<Program , Errors> {
+
+        let mut stmts = vec![];
+
+        while self.peek().is_some() {
+
+            if self.peek() == Some(&Token::SEMI) {
+                self.advance();
+                continue;
+            }
+
+            stmts.push(self.statement()?);
+
+        }
+
+        Ok(Program { stmts })
+
+    }
+
+    // parses one expression that has to use up every token
+    pub fn parse_expression(&mut self) -> Result<Expr , Errors> {
+
+        let expr = self.expression()?;
+
+        if let Some(token) = self.peek() {
+            return Err(self.error(ParseErrorKind::UnexpectedToken , Some(token.clone())));
+        }
+
+        Ok(expr)
+
+    }
+
+    // parses the next expression and leaves the tokens after it alone
+    pub fn expression(&mut self) -> Result<Expr , Errors> {
+        self.expression_above(ASSIGNMENT)
+    }
+
+    pub fn statement(&mut self) -> Result<Stmt , Errors> {
+        self.nested(Parser::any_statement)
+    }
+
+    fn any_statement(&mut self) -> Result<Stmt , Errors> {
+
+        let start = self.peek_span().start;
+
+        let kind = match self.peek() {
+            Some(Token::Keyword(Keyword::Let)) => self.let_statement()?,
+            Some(Token::Keyword(Keyword::Fn)) => self.fn_statement()?,
+            Some(Token::Keyword(Keyword::Return)) => {
+
+                self.advance();
+
+                // the value is left out when the statement ends right away
+                match self.peek() {
+                    None | Some(Token::SEMI) | Some(Token::RBR) => StmtKind::Return(None),
+                    _ => StmtKind::Return(Some(self.expression()?)),
+                }
+
+            }
+            Some(Token::Keyword(Keyword::If)) => return self.if_statement(),
+            Some(Token::Keyword(Keyword::While)) => {
+                self.advance();
+                let condition = self.expression()?;
+                let body = self.block()?;
+                StmtKind::While { condition , body }
+            }
+            Some(Token::Keyword(Keyword::Break)) => {
+                self.advance();
+                StmtKind::Break
+            }
+            Some(Token::Keyword(Keyword::Continue)) => {
+                self.advance();
+                StmtKind::Continue
+            }
+            Some(Token::LBR) => StmtKind::Block(self.block()?),
+            _ => StmtKind::Expr(self.expression()?),
+        };
+
+        let span = Span::new(start , self.previous_end());
+        self.skip_semicolon();
+
+        Ok(Stmt { kind , span })
+
+    }
+
+    // let name or let name = value
+    fn let_statement(&mut self) -> Result<StmtKind , Errors> {
+
+        self.advance();
+
+        let name = self.name()?;
+
+        if self.peek() != Some(&Token::Operator(Op::Assign)) {
+            return Ok(StmtKind::Let { name , value: None });
+        }
+
+        self.advance();
+
+        Ok(StmtKind::Let { name , value: Some(self.expression()?) })
+
+    }
+
+    // fn name(params) { body } , a trailing comma is allowed after the last parameter
+    fn fn_statement(&mut self) -> Result<StmtKind , Errors> {
+
+        self.advance();
+
+        let name = self.name()?;
+        self.expect(Token::LPAR)?;
+
+        let mut params = vec![];
+
+        while self.peek() != Some(&Token::RPAR) {
+
+            params.push(self.name()?);
+
+            if self.peek() != Some(&Token::COMMA) {
+                break;
+            }
+
+            self.advance();
+
+        }
+
+        self.expect(Token::RPAR)?;
+
+        Ok(StmtKind::Fn { name , params , body: self.block()? })
+
+    }
+
+    // moves past the next token if it is an ident and returns it
+    fn name(&mut self) -> Result<String , Errors> {
+
+        let name = match self.peek() {
+            Some(Token::Ident(name)) => name.clone(),
+            found => return Err(self.error(ParseErrorKind::ExpectedName , found.cloned())),
+        };
+
+        self.advance();
+
+        Ok(name)
+
+    }
+
+    // if condition { ... } with any number of else if and an optional else
+    // an else if is an if in the else branch , the branches are read in a loop and nested
+    // afterwards so a long chain of them doesn't count as deep nesting
+    fn if_statement(&mut self) -> Result<Stmt , Errors> {
+
+        // (start , condition , then_branch) of every if in the chain
+        let mut branches = vec![];
+        let mut chain = None;
+
+        loop {
+
+            let start = self.advance().unwrap().span.start;
+            let condition = self.expression()?;
+            let then_branch = self.block()?;
+
+            branches.push((start , condition , then_branch));
+
+            if self.peek() != Some(&Token::Keyword(Keyword::Else)) {
+                break;
+            }
+
+            self.advance();
+
+            if self.peek() != Some(&Token::Keyword(Keyword::If)) {
+                let block = self.block()?;
+                chain = Some(Box::new(Stmt { span: block.span , kind: StmtKind::Block(block) }));
+                break;
+            }
+
+        }
+
+        let end = self.previous_end();
+        self.skip_semicolon();
+
+        // every if of the chain ends where the chain does
+        for (start , condition , then_branch) in branches.into_iter().rev() {
+            let kind = StmtKind::If { condition , then_branch , else_branch: chain };
+            chain = Some(Box::new(Stmt { kind , span: Span::new(start , end) }));
+        }
+
+        Ok(*chain.unwrap())
+
+    }
+
+    // { statements }
+    pub fn block(&mut self) -> Result<Block , Errors> {
+
+        let start = self.expect(Token::LBR)?.start;
+        let mut stmts = vec![];
+
+        loop {
+            match self.peek() {
+                Some(Token::RBR) | None => break,
+                Some(Token::SEMI) => {
+                    self.advance();
+                }
+                _ => stmts.push(self.statement()?),
+            }
+        }
+
+        let end = self.expect(Token::RBR)?.end;
+
+        Ok(Block { stmts , span: Span::new(start , end) })
+
+    }
+
+    fn skip_semicolon(&mut self) {
+        if self.peek() == Some(&Token::SEMI) {
+            self.advance();
+        }
+    }
+
+    // where the last token that was parsed ends
+    fn previous_end(&self) -> Position {
+        match self.current.checked_sub(1).and_then(|index| self.tokens.get(index)) {
+            Some(token) => token.span.end,
+            None => self.end,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.current).map(|token| &token.token)
+    }
+
+    // the span of the next token or an empty span at the end of the source text
+    fn peek_span(&self) -> Span {
+        match self.tokens.get(self.current) {
+            Some(token) => token.span,
+            None => Span::new(self.end , self.end),
+        }
+    }
+
+    fn advance(&mut self) -> Option<SpannedToken> {
+        let token = self.tokens.get(self.current).cloned();
+        self.current += 1;
+        token
+    }
+
+    // makes a parse error about the next token
+    fn error(&self , kind: ParseErrorKind , found: Option<Token>) -> Errors {
+        Errors::ParseError(ParseError { kind , found , span: self.peek_span() })
+    }
+
+    // moves past the next token if it is the expected one or fails
+    fn expect(&mut self , expected: Token) -> Result<Span , Errors> {
+
+        match self.peek() {
+            Some(token) if *token == expected => Ok(self.advance().unwrap().span),
+            found => Err(self.error(ParseErrorKind::Expected(expected) , found.cloned())),
+        }
+
+    }
+
+    // parses one level deeper , too deep is an error
+    fn nested<T>(&mut self , parse: impl FnOnce(&mut Parser) -> Result<T , Errors>) -> Result<T , Errors> {
+
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(ParseErrorKind::TooDeep , self.peek().cloned()));
+        }
+
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+
+        result
+
+    }
+
+    // parses an expression made of operators with a precedence of at least min
+    fn expression_above(&mut self , min: u8) -> Result<Expr , Errors> {
+        self.nested(|parser| parser.operators_above(min))
+    }
+
+    fn operators_above(&mut self , min: u8) -> Result<Expr , Errors> {
+
+        let mut left = self.prefix()?;
+
+        loop {
+
+            if self.peek() == Some(&Token::LPAR) && CALL >= min {
+                left = self.call(left)?;
+                continue;
+            }
+
+            let op = match self.peek() {
+                Some(Token::Operator(op)) => *op,
+                _ => break,
+            };
+
+            let (precedence , right_associative) = match infix_precedence(op) {
+                Some(found) if found.0 >= min => found,
+                _ => break,
+            };
+
+            self.advance();
+
+            // a right associative operator lets the right side use the same operator again
+            let next = if right_associative { precedence } else { precedence + 1 };
+            let right = self.expression_above(next)?;
+            let span = Span::new(left.span.start , right.span.end);
+
+            let kind = if precedence == ASSIGNMENT {
+
+                let name = match left.kind {
+                    ExprKind::Ident(name) => name,
+                    _ => return Err(Errors::ParseError(ParseError {
+                        kind: ParseErrorKind::InvalidAssignmentTarget,
+                        found: None,
+                        span: left.span,
+                    })),
+                };
+
+                ExprKind::Assign { name , op , value: Box::new(right) }
+
+            } else {
+                ExprKind::Binary { op , left: Box::new(left) , right: Box::new(right) }
+            };
+
+            left = Expr { kind , span };
+
+        }
+
+        Ok(left)
+
+    }
+
+    // literals , names , groups and prefix operators
+    fn prefix(&mut self) -> Result<Expr , Errors> {
+
+        let token = match self.advance() {
+            Some(token) => token,
+            None => return Err(self.error(ParseErrorKind::ExpectedExpression , None)),
+        };
+
+        let span = token.span;
+
+        let kind = match token.token {
+            Token::Number(value) => ExprKind::Literal(Literal::Number(value)),
+            Token::Float(value) => ExprKind::Literal(Literal::Float(value)),
+            Token::BigInt(value) => ExprKind::Literal(Literal::BigInt(value)),
+            Token::Text(text) => ExprKind::Literal(Literal::Text(text)),
+            Token::Keyword(Keyword::True) => ExprKind::Literal(Literal::Bool(true)),
+            Token::Keyword(Keyword::False) => ExprKind::Literal(Literal::Bool(false)),
+            Token::Keyword(Keyword::Nil) => ExprKind::Literal(Literal::Nil),
+            Token::Ident(name) => ExprKind::Ident(name),
+            Token::Operator(op) if is_prefix(op) => {
+                let operand = self.expression_above(PREFIX)?;
+                let span = Span::new(span.start , operand.span.end);
+                return Ok(Expr { kind: ExprKind::Unary { op , operand: Box::new(operand) } , span });
+            }
+            Token::LPAR => {
+                let inner = self.expression()?;
+                let close = self.expect(Token::RPAR)?;
+                return Ok(Expr { kind: ExprKind::Grouping(Box::new(inner)) , span: Span::new(span.start , close.end) });
+            }
+            found => {
+                self.current -= 1;
+                return Err(self.error(ParseErrorKind::ExpectedExpression , Some(found)));
+            }
+        };
+
+        Ok(Expr { kind , span })
+
+    }
+
+    // the arguments of a call , the ( is the next token
+    // a trailing comma is allowed like f(a , b ,)
+    fn call(&mut self , callee: Expr) -> Result<Expr , Errors> {
+
+        self.advance();
+        let mut args = vec![];
+
+        while self.peek() != Some(&Token::RPAR) {
+
+            args.push(self.expression()?);
+
+            if self.peek() != Some(&Token::COMMA) {
+                break;
+            }
+
+            self.advance();
+
+        }
+
+        let close = self.expect(Token::RPAR)?;
+        let span = Span::new(callee.span.start , close.end);
+
+        Ok(Expr { kind: ExprKind::Call { callee: Box::new(callee) , args } , span })
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{lexer::{ImagineLexer , Lexer} , statemachine::ImagineMachine};
+
+    fn parse(text: &str) -> Result<Expr , Errors> {
+        let tokens = ImagineLexer::new(String::from(text) , ImagineMachine::new()).lex()?;
+        Parser::new(tokens).parse_expression()
+    }
+
+    fn parse_program(text: &str) -> Result<Program , Errors> {
+        let tokens = ImagineLexer::new(String::from(text) , ImagineMachine::new()).lex()?;
+        Parser::new(tokens).parse_program()
+    }
+
+    // writes the statements back on one line , used to compare whole programs
+    fn show_stmt(stmt: &Stmt) -> String {
+        match &stmt.kind {
+            StmtKind::Let { name , value: Some(value) } => format!("let {} = {};" , name , show(value)),
+            StmtKind::Let { name , value: None } => format!("let {};" , name),
+            StmtKind::Fn { name , params , body } => format!("fn {}({}) {}" , name , params.join(" , ") , show_block(body)),
+            StmtKind::Return(Some(value)) => format!("return {};" , show(value)),
+            StmtKind::Return(None) => String::from("return;"),
+            StmtKind::Expr(expr) => format!("{};" , show(expr)),
+            StmtKind::If { condition , then_branch , else_branch: Some(else_branch) } => {
+                format!("if {} {} else {}" , show(condition) , show_block(then_branch) , show_stmt(else_branch))
+            }
+            StmtKind::If { condition , then_branch , else_branch: None } => {
+                format!("if {} {}" , show(condition) , show_block(then_branch))
+            }
+            StmtKind::While { condition , body } => format!("while {} {}" , show(condition) , show_block(body)),
+            StmtKind::Block(block) => show_block(block),
+            StmtKind::Break => String::from("break;"),
+            StmtKind::Continue => String::from("continue;"),
+        }
+    }
+
+    fn show_block(block: &Block) -> String {
+        let stmts: Vec<String> = block.stmts.iter().map(show_stmt).collect();
+        format!("{{ {} }}" , stmts.join(" "))
+    }
+
+    fn show_program(program: &Program) -> String {
+        let stmts: Vec<String> = program.stmts.iter().map(show_stmt).collect();
+        stmts.join(" ")
+    }
+
+    // writes the expression back with every operation in parentheses
+    fn show(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Literal(Literal::Number(value)) => value.to_string(),
+            ExprKind::Literal(Literal::Float(value)) => format!("{:?}" , value),
+            ExprKind::Literal(Literal::BigInt(value)) => value.to_string(),
+            ExprKind::Literal(Literal::Text(text)) => format!("{:?}" , text),
+            ExprKind::Literal(Literal::Bool(value)) => value.to_string(),
+            ExprKind::Literal(Literal::Nil) => String::from("nil"),
+            ExprKind::Ident(name) => name.clone(),
+            ExprKind::Unary { op , operand } => format!("({}{})" , op , show(operand)),
+            ExprKind::Binary { op , left , right } => format!("({} {} {})" , show(left) , op , show(right)),
+            ExprKind::Assign { name , op , value } => format!("({} {} {})" , name , op , show(value)),
+            ExprKind::Grouping(inner) => show(inner),
+            ExprKind::Call { callee , args } => {
+                let args: Vec<String> = args.iter().map(show).collect();
+                format!("{}({})" , show(callee) , args.join(" , "))
+            }
+        }
+    }
+
+    fn error_of<T: std::fmt::Debug>(result: Result<T , Errors>) -> ParseError {
+        match result.unwrap_err() {
+            Errors::ParseError(error) => error,
+            error => panic!("unexpected error {:?}" , error),
+        }
+    }
+
+    #[test]
+    fn precedence() {
+
+        let cases = [
+            ("1 + 2 * 3" , "(1 + (2 * 3))"),
+            ("1 * 2 + 3" , "((1 * 2) + 3)"),
+            ("1 - 2 - 3" , "((1 - 2) - 3)"),
+            ("2 ** 3 ** 2" , "(2 ** (3 ** 2))"),
+            ("-2 ** 2" , "(-(2 ** 2))"),
+            ("2 ** -1" , "(2 ** (-1))"),
+            ("!a && b || c" , "(((!a) && b) || c)"),
+            ("a | b ^ c & d" , "(a | (b ^ (c & d)))"),
+            ("a == b < c << 1" , "(a == (b < (c << 1)))"),
+            ("a = b += c" , "(a = (b += c))"),
+            ("(1 + 2) * 3" , "((1 + 2) * 3)"),
+            ("--x" , "(-(-x))"),
+            ("~x + 1" , "((~x) + 1)"),
+        ];
+
+        for (text , expected) in cases {
+            assert_eq!(show(&parse(text).unwrap()) , expected , "{}" , text);
+        }
+
+    }
+
+    #[test]
+    fn calls_and_literals() {
+
+        assert_eq!(show(&parse("f(1 , g(x)(y) , \"s\" ,)").unwrap()) , "f(1 , g(x)(y) , \"s\")");
+        assert_eq!(show(&parse("-f()").unwrap()) , "(-f())");
+        assert_eq!(show(&parse("true != false").unwrap()) , "(true != false)");
+        assert_eq!(show(&parse("x == nil").unwrap()) , "(x == nil)");
+        assert_eq!(show(&parse("2.5 * x").unwrap()) , "(2.5 * x)");
+
+    }
+
+    #[test]
+    fn spans() {
+
+        let expr = parse("f(a + 1)  * (b)").unwrap();
+        assert_eq!((expr.span.start.offset , expr.span.end.offset) , (0 , 15));
+
+        let ExprKind::Binary { left , right , .. } = expr.kind else { panic!("expected a binary expression") };
+        assert_eq!((left.span.start.offset , left.span.end.offset) , (0 , 8));
+        assert_eq!((right.span.start.offset , right.span.end.offset) , (12 , 15));
+
+    }
+
+    #[test]
+    fn parse_errors() {
+
+        let error = error_of(parse("(1 + 2"));
+        assert_eq!(error.kind , ParseErrorKind::Expected(Token::RPAR));
+        assert_eq!(error.found , None);
+        assert_eq!(error.span.start.offset , 6);
+
+        let error = error_of(parse("1 + * 2"));
+        assert_eq!(error.kind , ParseErrorKind::ExpectedExpression);
+        assert_eq!(error.found , Some(Token::Operator(Op::Star)));
+        assert_eq!(error.span.start.offset , 4);
+
+        let error = error_of(parse("1 2"));
+        assert_eq!(error.kind , ParseErrorKind::UnexpectedToken);
+        assert_eq!(error.found , Some(Token::Number(2)));
+
+        let error = error_of(parse("1 + 2 = 3"));
+        assert_eq!(error.kind , ParseErrorKind::InvalidAssignmentTarget);
+        assert_eq!((error.span.start.offset , error.span.end.offset) , (0 , 5));
+
+        assert_eq!(error_of(parse("")).kind , ParseErrorKind::ExpectedExpression);
+        assert_eq!(error_of(parse("f(a b)")).kind , ParseErrorKind::Expected(Token::RPAR));
+
+        // the error is where the nesting goes too deep
+        for text in ["(".repeat(100_000) , "-".repeat(100_000) , "{".repeat(100_000) , "if x { ".repeat(100_000)] {
+            let error = error_of(parse_program(&text));
+            assert_eq!(error.kind , ParseErrorKind::TooDeep);
+            assert!(error.span.start.offset < MAX_DEPTH * 13 , "{}" , error.span.start.offset);
+        }
+
+        // a long else if chain is not nested
+        let chain = format!("{}{{}}" , "if x {} else ".repeat(1000));
+        assert_eq!(parse_program(&chain).unwrap().stmts.len() , 1);
+
+        assert_eq!(
+            Errors::ParseError(error_of(parse("(1"))).to_string(),
+            "parse error at 1:3: expected ) at the end of input",
+        );
+
+    }
+
+    #[test]
+    fn statements() {
+
+        let program = parse_program("
+            let x = 1
+            let y;
+            x += 2 y = x * 2;
+            while x < 10 { x = x + 1 if x == 5 { break } }
+            if a { b } else if c { d; } else { e }
+            { let z continue }
+        ").unwrap();
+
+        assert_eq!(show_program(&program) , [
+            "let x = 1;",
+            "let y;",
+            "(x += 2);",
+            "(y = (x * 2));",
+            "while (x < 10) { (x = (x + 1)); if (x == 5) { break; } }",
+            "if a { b; } else if c { d; } else { e; }",
+            "{ let z; continue; }",
+        ].join(" "));
+
+    }
+
+    #[test]
+    fn functions() {
+
+        let program = parse_program("
+            fn add(a , b ,) { return a + b }
+            fn nothing() { return }
+            fn early(x) { if x { return; } return x * 2; }
+            print(add(1 , 2) , nothing())
+        ").unwrap();
+
+        assert_eq!(show_program(&program) , [
+            "fn add(a , b) { return (a + b); }",
+            "fn nothing() { return; }",
+            "fn early(x) { if x { return; } return (x * 2); }",
+            "print(add(1 , 2) , nothing());",
+        ].join(" "));
+
+        let error = error_of(parse_program("fn f(a , 1) {}"));
+        assert_eq!(error.kind , ParseErrorKind::ExpectedName);
+        assert_eq!(error.found , Some(Token::Number(1)));
+
+        assert_eq!(error_of(parse_program("fn (a) {}")).kind , ParseErrorKind::ExpectedName);
+        assert_eq!(error_of(parse_program("fn f a {}")).kind , ParseErrorKind::Expected(Token::LPAR));
+        assert_eq!(error_of(parse_program("fn f(a b) {}")).kind , ParseErrorKind::Expected(Token::RPAR));
+        assert_eq!(error_of(parse_program("fn f()")).kind , ParseErrorKind::Expected(Token::LBR));
+
+    }
+
+    #[test]
+    fn statement_spans() {
+
+        let program = parse_program("let x = 1;\nif x {\n  x\n}").unwrap();
+        let spans: Vec<(usize , usize)> = program.stmts.iter()
+            .map(|stmt| (stmt.span.start.offset , stmt.span.end.offset))
+            .collect();
+
+        assert_eq!(spans , vec![(0 , 9) , (11 , 23)]);
+
+    }
+
+    #[test]
+    fn statement_errors() {
+
+        let error = error_of(parse_program("let 1 = 2"));
+        assert_eq!(error.kind , ParseErrorKind::ExpectedName);
+        assert_eq!(error.found , Some(Token::Number(1)));
+        assert_eq!((error.span.start.line , error.span.start.column) , (1 , 5));
+
+        let error = error_of(parse_program("while x\n  y = 1"));
+        assert_eq!(error.kind , ParseErrorKind::Expected(Token::LBR));
+        assert_eq!(error.found , Some(Token::Ident(String::from("y"))));
+        assert_eq!((error.span.start.line , error.span.start.column) , (2 , 3));
+
+        let error = error_of(parse_program("if x { y"));
+        assert_eq!(error.kind , ParseErrorKind::Expected(Token::RBR));
+        assert_eq!(error.found , None);
+
+        assert_eq!(error_of(parse_program("else { }")).kind , ParseErrorKind::ExpectedExpression);
+        assert_eq!(error_of(parse_program("x }")).kind , ParseErrorKind::ExpectedExpression);
+
+    }
+
+}
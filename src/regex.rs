@@ -0,0 +1,404 @@
+use crate::{
+    common::{RegexError , RegexErrorKind},
+    dfa::{CharClass , Pattern},
+};
+
+// a small regex syntax for the token rules of dfa::MachineBuilder
+//
+// abc         the text
+// .           any character but a line break
+// [a-z_]      a class , [^...] is every character not in it
+// \d \w \s    ascii digits , word characters and whitespace , \D \W \S are the rest
+// \n \t \r    line feed , tab and carriage return , any other \ followed by a symbol is the symbol
+// a|b         either one
+// (ab)        a group
+// * + ?       zero or more , one or more , zero or one
+// {n} {n,} {n,m}  n times , at least n times , n to m times
+//
+// there are no anchors or lazy repetitions , the machine always takes the longest match anyway
+
+// the most a {} repetition can repeat , the pattern is copied that many times
+pub const MAX_REPETITION: usize = 256;
+
+// the most nodes a {} repetition can make , repetitions inside repetitions multiply
+// so a{256} is fine but ((a{256}){256}){256} would be millions of NFA states
+pub const MAX_SIZE: usize = 4096;
+
+// the characters that mean something in a regex
+const SPECIAL: &str = "\\.[]()|*+?{}^$-/";
+
+pub fn parse(regex: &str) -> Result<Pattern , RegexError> {
+
+    let mut parser = RegexParser { chars: regex.chars().collect() , position: 0 };
+    let pattern = parser.alternation()?;
+
+    // alternation only stops early at a )
+    if parser.position < parser.chars.len() {
+        return Err(parser.error(RegexErrorKind::UnmatchedParen));
+    }
+
+    Ok(pattern)
+
+}
+
+fn word() -> CharClass {
+    CharClass::ascii_alphanumeric().union(&CharClass::chars("_"))
+}
+
+// the nodes of the pattern with a literal counted by its characters , about the NFA states it needs
+fn size(pattern: &Pattern) -> usize {
+    match pattern {
+        Pattern::Literal(text) => text.chars().count().max(1),
+        Pattern::Class(_) => 1,
+        Pattern::Concat(patterns) | Pattern::Alternate(patterns) => 1 + patterns.iter().map(size).sum::<usize>(),
+        Pattern::Star(pattern) | Pattern::Plus(pattern) | Pattern::Optional(pattern) => 1 + size(pattern),
+    }
+}
+
+// the class of a \d , \w or \s escape
+fn class_escape(input: char) -> Option<CharClass> {
+    match input {
+        'd' => Some(CharClass::ascii_digit()),
+        'w' => Some(word()),
+        's' => Some(CharClass::whitespace()),
+        'D' => Some(CharClass::ascii_digit().negate()),
+        'W' => Some(word().negate()),
+        'S' => Some(CharClass::whitespace().negate()),
+        _ => None,
+    }
+}
+
+// the character of an escape that stands for one
+fn char_escape(input: char) -> Option<char> {
+    match input {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '0' => Some('\0'),
+        input if !input.is_alphanumeric() => Some(input),
+        _ => None,
+    }
+}
+
+// recursive descent over the characters of the regex , position is the next character
+struct RegexParser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl RegexParser {
+
+    fn error(&self , kind: RegexErrorKind) -> RegexError {
+        RegexError { kind , position: self.position }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let input = self.peek();
+        self.position += 1;
+        input
+    }
+
+    fn eat(&mut self , expected: char) -> bool {
+
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            return true;
+        }
+
+        false
+
+    }
+
+    // concat ( | concat )*
+    fn alternation(&mut self) -> Result<Pattern , RegexError> {
+
+        let mut patterns = vec![self.concat()?];
+
+        while self.eat('|') {
+            patterns.push(self.concat()?);
+        }
+
+        match patterns.len() {
+            1 => Ok(patterns.pop().unwrap()),
+            _ => Ok(Pattern::Alternate(patterns)),
+        }
+
+    }
+
+    // repeats up to a | , a ) or the end , the characters next to each other become one literal
+    fn concat(&mut self) -> Result<Pattern , RegexError> {
+
+        let mut patterns: Vec<Pattern> = vec![];
+
+        while let Some(input) = self.peek() {
+
+            if input == '|' || input == ')' {
+                break;
+            }
+
+            match (patterns.last_mut() , self.repeat()?) {
+                (Some(Pattern::Literal(text)) , Pattern::Literal(next)) => text.push_str(&next),
+                (_ , pattern) => patterns.push(pattern),
+            }
+
+        }
+
+        match patterns.len() {
+            1 => Ok(patterns.pop().unwrap()),
+            _ => Ok(Pattern::Concat(patterns)),
+        }
+
+    }
+
+    // an atom and the repetitions after it
+    fn repeat(&mut self) -> Result<Pattern , RegexError> {
+
+        let mut pattern = self.atom()?;
+
+        loop {
+            pattern = match self.peek() {
+                Some('*') => pattern.star(),
+                Some('+') => pattern.plus(),
+                Some('?') => pattern.optional(),
+                Some('{') => {
+                    pattern = self.bounded(pattern)?;
+                    continue;
+                }
+                _ => return Ok(pattern),
+            };
+            self.position += 1;
+        }
+
+    }
+
+    // {n} {n,} or {n,m} , the pattern is copied n times and then made optional or repeated
+    fn bounded(&mut self , pattern: Pattern) -> Result<Pattern , RegexError> {
+
+        let start = self.position;
+        self.position += 1;
+
+        let invalid = RegexError { kind: RegexErrorKind::InvalidRepetition , position: start };
+
+        let min = self.number().ok_or(invalid.clone())?;
+        let max = match self.eat(',') {
+            true if self.peek() == Some('}') => None,
+            true => Some(self.number().ok_or(invalid.clone())?),
+            false => Some(min),
+        };
+
+        if !self.eat('}') || max.is_some_and(|max| max < min) || max.unwrap_or(min) > MAX_REPETITION {
+            return Err(invalid);
+        }
+
+        // {n,} makes n copies and a star of one more
+        if size(&pattern) * max.unwrap_or(min + 1) > MAX_SIZE {
+            return Err(invalid);
+        }
+
+        let mut patterns = vec![pattern.clone() ; min];
+
+        match max {
+            Some(max) => patterns.extend(vec![pattern.optional() ; max - min]),
+            None => patterns.push(pattern.star()),
+        }
+
+        match patterns.len() {
+            1 => Ok(patterns.pop().unwrap()),
+            _ => Ok(Pattern::Concat(patterns)),
+        }
+
+    }
+
+    fn number(&mut self) -> Option<usize> {
+
+        let start = self.position;
+
+        while self.peek().is_some_and(|input| input.is_ascii_digit()) {
+            self.position += 1;
+        }
+
+        self.chars[start..self.position].iter().collect::<String>().parse().ok()
+
+    }
+
+    fn atom(&mut self) -> Result<Pattern , RegexError> {
+
+        let start = self.position;
+
+        match self.next() {
+            Some('(') => {
+
+                let pattern = self.alternation()?;
+
+                if !self.eat(')') {
+                    return Err(RegexError { kind: RegexErrorKind::UnclosedGroup , position: start });
+                }
+
+                Ok(pattern)
+
+            }
+            Some('[') => self.class(start),
+            Some('.') => Ok(Pattern::Class(CharClass::chars("\n").negate())),
+            Some('\\') => match self.escape()? {
+                Ok(input) => Ok(Pattern::Literal(input.to_string())),
+                Err(class) => Ok(Pattern::Class(class)),
+            },
+            Some('*' | '+' | '?' | '{') => Err(RegexError { kind: RegexErrorKind::NothingToRepeat , position: start }),
+            Some(input) => Ok(Pattern::Literal(input.to_string())),
+            // concat doesn't ask for an atom at the end
+            None => unreachable!(),
+        }
+
+    }
+
+    // the rest of an escape after the \ , a character or a class
+    fn escape(&mut self) -> Result<Result<char , CharClass> , RegexError> {
+
+        let start = self.position - 1;
+        let invalid = RegexError { kind: RegexErrorKind::InvalidEscape , position: start };
+        let input = self.next().ok_or(invalid.clone())?;
+
+        if let Some(class) = class_escape(input) {
+            return Ok(Err(class));
+        }
+
+        char_escape(input).map(Ok).ok_or(invalid)
+
+    }
+
+    // the inside of [] after the [ , a - is a character at the start or the end
+    fn class(&mut self , start: usize) -> Result<Pattern , RegexError> {
+
+        let negated = self.eat('^');
+        let mut class = CharClass::new();
+
+        loop {
+
+            let first = match self.next() {
+                None => return Err(RegexError { kind: RegexErrorKind::UnclosedClass , position: start }),
+                Some(']') => break,
+                Some('\\') => match self.escape()? {
+                    Ok(input) => input,
+                    Err(escaped) => {
+                        class = class.union(&escaped);
+                        continue;
+                    }
+                },
+                Some(input) => input,
+            };
+
+            let is_range = self.peek() == Some('-') && !matches!(self.chars.get(self.position + 1) , Some(']') | None);
+
+            if !is_range {
+                class = class.union(&CharClass::range(first , first));
+                continue;
+            }
+
+            let range_start = self.position - 1;
+            self.position += 1;
+
+            let last = match self.next() {
+                Some('\\') => match self.escape()? {
+                    Ok(input) => input,
+                    Err(_) => return Err(RegexError { kind: RegexErrorKind::InvalidRange , position: range_start }),
+                },
+                Some(input) => input,
+                None => unreachable!(),
+            };
+
+            if last < first {
+                return Err(RegexError { kind: RegexErrorKind::InvalidRange , position: range_start });
+            }
+
+            class = class.union(&CharClass::range(first , last));
+
+        }
+
+        if negated {
+            class = class.negate();
+        }
+
+        Ok(Pattern::Class(class))
+
+    }
+
+}
+
+// escapes the text so it can be used in a regex as it is
+pub fn escape(text: &str) -> String {
+
+    let mut escaped = String::with_capacity(text.len());
+
+    for input in text.chars() {
+        if SPECIAL.contains(input) {
+            escaped.push('\\');
+        }
+        escaped.push(input);
+    }
+
+    escaped
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn class(text: &str) -> Pattern {
+        Pattern::Class(CharClass::chars(text))
+    }
+
+    #[test]
+    fn syntax() {
+
+        assert_eq!(parse("abc").unwrap() , Pattern::literal("abc"));
+        assert_eq!(parse("ab*c").unwrap() , Pattern::Concat(vec![Pattern::literal("a") , Pattern::literal("b").star() , Pattern::literal("c")]));
+        assert_eq!(parse("a|bc|").unwrap() , Pattern::Alternate(vec![Pattern::literal("a") , Pattern::literal("bc") , Pattern::Concat(vec![])]));
+        assert_eq!(parse("(a|b)+?").unwrap() , Pattern::literal("a").or(Pattern::literal("b")).plus().optional());
+        assert_eq!(parse("[a-c_]").unwrap() , Pattern::Class(CharClass::range('a' , 'c').union(&CharClass::chars("_"))));
+        assert_eq!(parse("[^\\d-]").unwrap() , Pattern::Class(CharClass::ascii_digit().union(&CharClass::chars("-")).negate()));
+        assert_eq!(parse("[-a]").unwrap() , class("-a"));
+        assert_eq!(parse("\\.\\n\\\\").unwrap() , Pattern::literal(".\n\\"));
+        assert_eq!(parse("\\s").unwrap() , Pattern::Class(CharClass::whitespace()));
+
+        assert_eq!(parse("x{2}").unwrap() , Pattern::Concat(vec![Pattern::literal("x") , Pattern::literal("x")]));
+        assert_eq!(parse("x{1,}").unwrap() , Pattern::Concat(vec![Pattern::literal("x") , Pattern::literal("x").star()]));
+        assert_eq!(parse("x{0,2}").unwrap() , Pattern::Concat(vec![Pattern::literal("x").optional() , Pattern::literal("x").optional()]));
+
+        assert_eq!(parse(&escape("a.b*(c)")).unwrap() , Pattern::literal("a.b*(c)"));
+
+    }
+
+    #[test]
+    fn errors() {
+
+        let error = |regex: &str| {
+            let error = parse(regex).unwrap_err();
+            (error.kind , error.position)
+        };
+
+        assert_eq!(error("(ab") , (RegexErrorKind::UnclosedGroup , 0));
+        assert_eq!(error("ab)") , (RegexErrorKind::UnmatchedParen , 2));
+        assert_eq!(error("a[bc") , (RegexErrorKind::UnclosedClass , 1));
+        assert_eq!(error("*a") , (RegexErrorKind::NothingToRepeat , 0));
+        assert_eq!(error("a|+") , (RegexErrorKind::NothingToRepeat , 2));
+        assert_eq!(error("\\q") , (RegexErrorKind::InvalidEscape , 0));
+        assert_eq!(error("a\\") , (RegexErrorKind::InvalidEscape , 1));
+        assert_eq!(error("[z-a]") , (RegexErrorKind::InvalidRange , 1));
+        assert_eq!(error("a{3,1}") , (RegexErrorKind::InvalidRepetition , 1));
+        assert_eq!(error("a{x}") , (RegexErrorKind::InvalidRepetition , 1));
+        assert_eq!(error("a{1000}") , (RegexErrorKind::InvalidRepetition , 1));
+        assert_eq!(error("((a{256}){256}){256}") , (RegexErrorKind::InvalidRepetition , 9));
+        assert_eq!(error("(){256}{256}") , (RegexErrorKind::InvalidRepetition , 7));
+        assert!(parse("(ab|c){256}").is_ok());
+
+        assert_eq!(parse("(").unwrap_err().to_string() , "invalid regex at 0: unclosed group");
+
+    }
+
+}
@@ -0,0 +1,270 @@
+use std::io::{BufRead , ErrorKind};
+
+use crate::common::Errors;
+
+// where the lexer reads its characters from
+// peek_char returns the current character without moving past it
+// and next_char moves past it , both return None at the end of the input
+pub trait Source {
+    fn peek_char(&mut self) -> Result<Option<char> , Errors>;
+    fn next_char(&mut self) -> Result<Option<char> , Errors>;
+}
+
+// the whole source text is already in memory
+// offset is the byte offset of the current character
+pub struct TextSource {
+    text: String,
+    offset: usize,
+}
+
+impl TextSource {
+    pub fn new(text: String) -> TextSource {
+        TextSource { text , offset: 0 }
+    }
+}
+
+impl Source for TextSource {
+
+    fn peek_char(&mut self) -> Result<Option<char> , Errors> {
+        Ok(self.text[self.offset..].chars().next())
+    }
+
+    fn next_char(&mut self) -> Result<Option<char> , Errors> {
+
+        let input = self.peek_char()?;
+
+        if let Some(input) = input {
+            self.offset += input.len_utf8();
+        }
+
+        Ok(input)
+
+    }
+
+}
+
+// the source text arrives in pieces , a new chunk is only taken once the current one is used up
+// chunks are &str (or String) so a character can never be split between two of them
+pub struct ChunkSource<I: Iterator> {
+    chunks: I,
+    chunk: Option<I::Item>,
+    offset: usize,
+}
+
+impl<I: Iterator> ChunkSource<I> where I::Item: AsRef<str> {
+    pub fn new(chunks: I) -> ChunkSource<I> {
+        ChunkSource { chunks , chunk: None , offset: 0 }
+    }
+}
+
+impl<I: Iterator> Source for ChunkSource<I> where I::Item: AsRef<str> {
+
+    fn peek_char(&mut self) -> Result<Option<char> , Errors> {
+
+        loop {
+
+            if let Some(chunk) = &self.chunk {
+                if let Some(input) = chunk.as_ref()[self.offset..].chars().next() {
+                    return Ok(Some(input));
+                }
+            }
+
+            // the current chunk is used up (or empty) so move on to the next one
+            self.chunk = self.chunks.next();
+            self.offset = 0;
+
+            if self.chunk.is_none() {
+                return Ok(None);
+            }
+
+        }
+
+    }
+
+    fn next_char(&mut self) -> Result<Option<char> , Errors> {
+
+        let input = self.peek_char()?;
+
+        if let Some(input) = input {
+            self.offset += input.len_utf8();
+        }
+
+        Ok(input)
+
+    }
+
+}
+
+// reads the source text from anything that implements BufRead like a file or stdin
+// bytes are decoded as they arrive , text holds the decoded part of the last read
+// and pending holds the start of a character whose remaining bytes are not read yet
+pub struct ReaderSource<R: BufRead> {
+    reader: R,
+    text: String,
+    offset: usize,
+    pending: Vec<u8>,
+}
+
+impl<R: BufRead> ReaderSource<R> {
+
+    pub fn new(reader: R) -> ReaderSource<R> {
+        ReaderSource { reader , text: String::new() , offset: 0 , pending: vec![] }
+    }
+
+    // reads more bytes until at least one character is decoded
+    // returns false at the end of the input
+    fn fill(&mut self) -> Result<bool , Errors> {
+
+        loop {
+
+            // decode everything up to the first character that is cut off or invalid
+            let (valid , invalid) = match std::str::from_utf8(&self.pending) {
+                Ok(text) => (text.len() , false),
+                Err(error) => (error.valid_up_to() , error.error_len().is_some()),
+            };
+
+            if valid > 0 {
+
+                let rest = self.pending.split_off(valid);
+                let decoded = std::mem::replace(&mut self.pending , rest);
+
+                // from_utf8 already checked these bytes
+                self.text = String::from_utf8(decoded).unwrap();
+                self.offset = 0;
+
+                return Ok(true);
+
+            }
+
+            if invalid {
+                return Err(Errors::IoError(String::from("stream did not contain valid UTF-8")));
+            }
+
+            let bytes = match self.reader.fill_buf() {
+                Ok(bytes) => bytes,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(Errors::IoError(error.to_string())),
+            };
+
+            if bytes.is_empty() {
+
+                if !self.pending.is_empty() {
+                    return Err(Errors::IoError(String::from("stream ended in the middle of a UTF-8 character")));
+                }
+
+                return Ok(false);
+
+            }
+
+            self.pending.extend_from_slice(bytes);
+            let read = bytes.len();
+            self.reader.consume(read);
+
+        }
+
+    }
+
+}
+
+impl<R: BufRead> Source for ReaderSource<R> {
+
+    fn peek_char(&mut self) -> Result<Option<char> , Errors> {
+
+        if let Some(input) = self.text[self.offset..].chars().next() {
+            return Ok(Some(input));
+        }
+
+        if !self.fill()? {
+            return Ok(None);
+        }
+
+        Ok(self.text.chars().next())
+
+    }
+
+    fn next_char(&mut self) -> Result<Option<char> , Errors> {
+
+        let input = self.peek_char()?;
+
+        if let Some(input) = input {
+            self.offset += input.len_utf8();
+        }
+
+        Ok(input)
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::{BufReader , Read};
+
+    use super::*;
+
+    fn read_all(source: &mut impl Source) -> Result<String , Errors> {
+
+        let mut text = String::new();
+
+        while let Some(input) = source.next_char()? {
+            text.push(input);
+        }
+
+        Ok(text)
+
+    }
+
+    // hands out the bytes a few at a time no matter how much is asked for
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self , buf: &mut [u8]) -> std::io::Result<usize> {
+            let count = self.step.min(self.bytes.len()).min(buf.len());
+            buf[..count].copy_from_slice(&self.bytes[..count]);
+            self.bytes = &self.bytes[count..];
+            Ok(count)
+        }
+    }
+
+    #[test]
+    fn chunk_source() {
+
+        let mut source = ChunkSource::new(["le" , "" , "t x" , "é" , "" ].into_iter());
+
+        assert_eq!(source.peek_char().unwrap() , Some('l'));
+        assert_eq!(read_all(&mut source).unwrap() , "let xé");
+        assert_eq!(source.next_char().unwrap() , None);
+
+    }
+
+    #[test]
+    fn reader_source_split_characters() {
+
+        let text = "名前 = \"héllo 😀\"";
+
+        for step in 1..5 {
+            let reader = BufReader::with_capacity(step , Trickle { bytes: text.as_bytes() , step });
+            assert_eq!(read_all(&mut ReaderSource::new(reader)).unwrap() , text);
+        }
+
+    }
+
+    #[test]
+    fn reader_source_invalid_utf8() {
+
+        let reader = BufReader::new(&[b'a' , 0xff , b'b'][..]);
+        let mut source = ReaderSource::new(reader);
+
+        assert_eq!(source.next_char().unwrap() , Some('a'));
+        assert!(matches!(source.next_char() , Err(Errors::IoError(_))));
+
+        let reader = BufReader::new(&[b'a' , 0xe5 , 0x90][..]);
+        assert!(matches!(read_all(&mut ReaderSource::new(reader)) , Err(Errors::IoError(_))));
+
+    }
+
+}
@@ -1,561 +1,1484 @@
-use crate::common::Errors;
+use std::{borrow::Cow , fmt , str::FromStr};
 
-#[derive(PartialEq , Debug)]
+use crate::{
+    bigint::BigInt,
+    common::{Errors , LexError , LexErrorKind , NumberError , Position , Span , UnknownToken},
+};
+
+#[derive(PartialEq , Debug , Clone)]
 pub enum Token {
-    Number(i32),
-    Operator(String),
-    Float(f32),
+    Number(i64),
+    Operator(Op),
+    Float(f64),
+    // integer literals that don't fit in an i64 , only emitted if the machine is asked to
+    BigInt(BigInt),
     Ident(String),
-    Keyword(String),
+    Keyword(Keyword),
     LPAR,
     RPAR,
     LBR,
     RBR,
-    // used outside of the Machine
+    // punctuation , named like the tokens of python
+    COMMA,
+    SEMI,
+    LSQB,
+    RSQB,
+    DOT,
+    COLON,
+    // ::
+    DCOLON,
+    // ->
+    RARROW,
+    // =>
+    FATARROW,
+    // string literals
     Text(String),
+    // only emitted if the machine is asked to keep comments
+    Comment(String),
+    // text the lexer could not make sense of (see Lexer::lex_recovering)
+    Error(String),
+    // the end of the input , only emitted if the lexer is asked to (see ImagineLexer::emit_eof)
+    Eof,
     Blank,
+    // a token of a lexer made with dfa::MachineBuilder
+    Custom(Box<CustomToken>),
 }
 
-fn is_operator(input: &char) -> bool {
-    ['+', '-' , '=' , '/' , '*' , '!' , '<' , '>' , '~' , '|' , '&' , '^'].contains(input)
+// kind is the name of the rule that matched the text
+// it is boxed so the tokens and the errors that hold them stay small
+#[derive(PartialEq , Debug , Clone)]
+pub struct CustomToken {
+    pub kind: String,
+    pub text: String,
 }
 
-// every transition returns the new state and optinaly a token
-pub type Update = (Box<dyn State> , Option<Token>);
-
-const KEYWORD_LIST: &'static [&str] = &["if"];
-
-// represents the state of the current token being processed
-// get_type and get_value methods are used for debugging and testing
-pub trait State {
-
-    // returns the new state to transition and optinaly a token if everything went ok
-    fn feed(&self , input: char) -> Result<Update , Errors>;
-    fn get_type(&self) -> String;
-    fn get_token(&self) -> Token;
+// a token together with the part of the source text it came from
+#[derive(PartialEq , Debug , Clone)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
 
-    // only used for testing
-    fn get_value(&self) -> String {
-        return String::from("");
+// how the token looks in the source text , used in error messages
+impl fmt::Display for Token {
+
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f , "{}" , value),
+            Token::Operator(op) => write!(f , "{}" , op),
+            Token::Float(value) => write!(f , "{:?}" , value),
+            Token::BigInt(value) => write!(f , "{}" , value),
+            Token::Ident(name) => write!(f , "{}" , name),
+            Token::Keyword(keyword) => write!(f , "{}" , keyword),
+            Token::LPAR => write!(f , "("),
+            Token::RPAR => write!(f , ")"),
+            Token::LBR => write!(f , "{{"),
+            Token::RBR => write!(f , "}}"),
+            Token::COMMA => write!(f , ","),
+            Token::SEMI => write!(f , ";"),
+            Token::LSQB => write!(f , "["),
+            Token::RSQB => write!(f , "]"),
+            Token::DOT => write!(f , "."),
+            Token::COLON => write!(f , ":"),
+            Token::DCOLON => write!(f , "::"),
+            Token::RARROW => write!(f , "->"),
+            Token::FATARROW => write!(f , "=>"),
+            Token::Text(text) => write!(f , "{:?}" , text),
+            Token::Comment(text) | Token::Error(text) => write!(f , "{}" , text),
+            Token::Eof => write!(f , "end of input"),
+            Token::Blank => Ok(()),
+            Token::Custom(custom) => write!(f , "{}" , custom.text),
+        }
     }
 
 }
 
-// input handling and transition and token emitting logic is implemented
-// un structs that implement the State trait
+impl Token {
 
-// used for all the single/double character operators
-// + , - , * , / , == and etc.
+    pub fn custom(kind: &str , text: &str) -> Token {
+        Token::Custom(Box::new(CustomToken { kind: String::from(kind) , text: String::from(text) }))
+    }
 
-struct Lpar;
-struct Rpar;
-struct Lbr;
-struct Rbr;
+    // the name of the variant without its value , for tools that list tokens
+    pub fn get_type(&self) -> &'static str {
+        match self {
+            Token::Number(_) => "Number",
+            Token::Operator(_) => "Operator",
+            Token::Float(_) => "Float",
+            Token::BigInt(_) => "BigInt",
+            Token::Ident(_) => "Ident",
+            Token::Keyword(_) => "Keyword",
+            Token::LPAR => "LPAR",
+            Token::RPAR => "RPAR",
+            Token::LBR => "LBR",
+            Token::RBR => "RBR",
+            Token::COMMA => "COMMA",
+            Token::SEMI => "SEMI",
+            Token::LSQB => "LSQB",
+            Token::RSQB => "RSQB",
+            Token::DOT => "DOT",
+            Token::COLON => "COLON",
+            Token::DCOLON => "DCOLON",
+            Token::RARROW => "RARROW",
+            Token::FATARROW => "FATARROW",
+            Token::Text(_) => "Text",
+            Token::Comment(_) => "Comment",
+            Token::Error(_) => "Error",
+            Token::Eof => "Eof",
+            Token::Blank => "Blank",
+            Token::Custom(_) => "Custom",
+        }
+    }
 
-impl State for Lpar {
+}
 
-    fn feed(&self , input: char) -> Result<Update , Errors> {
+// the keywords of KEYWORD_LIST , any other word a machine is told is a keyword is a Custom one
+#[derive(Debug , Clone , PartialEq , Eq , Hash)]
+pub enum Keyword {
+    If,
+    Else,
+    While,
+    For,
+    Fn,
+    Let,
+    Return,
+    True,
+    False,
+    Nil,
+    Break,
+    Continue,
+    Custom(String),
+}
 
-        if input.is_whitespace() || input == '\n'  {
-            let new_state = Box::new(Blank {});
-            let token = self.get_token();
-            return Ok((new_state , Some(token)));
+impl Keyword {
+
+    const BUILTIN: &'static [Keyword] = &[
+        Keyword::If,
+        Keyword::Else,
+        Keyword::While,
+        Keyword::For,
+        Keyword::Fn,
+        Keyword::Let,
+        Keyword::Return,
+        Keyword::True,
+        Keyword::False,
+        Keyword::Nil,
+        Keyword::Break,
+        Keyword::Continue,
+    ];
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Keyword::If => "if",
+            Keyword::Else => "else",
+            Keyword::While => "while",
+            Keyword::For => "for",
+            Keyword::Fn => "fn",
+            Keyword::Let => "let",
+            Keyword::Return => "return",
+            Keyword::True => "true",
+            Keyword::False => "false",
+            Keyword::Nil => "nil",
+            Keyword::Break => "break",
+            Keyword::Continue => "continue",
+            Keyword::Custom(word) => word,
         }
-        
-        let new_state = (Blank {}).feed(input).unwrap().0;
-        let token = self.get_token();
-        return Ok((new_state , Some(token)));
-
     }
 
-    fn get_token(&self) -> Token {
-        Token::LPAR
-    }
+}
 
-    fn get_type(&self) -> String {
-        String::from("Lpar")
-    }
+impl fmt::Display for Keyword {
 
-    fn get_value(&self) -> String {
-        String::from("(")
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.as_str())
     }
 
 }
 
-impl State for Rpar {
+// any word that could be an ident is a keyword , the ones that are not built in are Custom
+impl FromStr for Keyword {
+
+    type Err = UnknownToken;
 
-    fn feed(&self , input: char) -> Result<Update , Errors> {
+    fn from_str(text: &str) -> Result<Keyword , UnknownToken> {
 
-        if input.is_whitespace() || input == '\n'  {
-            let new_state = Box::new(Blank {});
-            let token = self.get_token();
-            return Ok((new_state , Some(token)));
+        if let Some(keyword) = Keyword::BUILTIN.iter().find(|keyword| keyword.as_str() == text) {
+            return Ok(keyword.clone());
         }
-        
-        let new_state = (Blank {}).feed(input).unwrap().0;
-        let token = self.get_token();
-        return Ok((new_state , Some(token)));
 
-    }
+        let mut chars = text.chars();
+        let starts_word = chars.next().is_some_and(|first| first.is_alphabetic() || first == '_');
 
-    fn get_token(&self) -> Token {
-        Token::RPAR
-    }
+        if !starts_word || !chars.all(|input| input.is_alphanumeric() || input == '_') {
+            return Err(UnknownToken(String::from(text)));
+        }
 
-    fn get_type(&self) -> String {
-        String::from("Rpar")
-    }
+        Ok(Keyword::Custom(String::from(text)))
 
-    fn get_value(&self) -> String {
-        String::from(")")
     }
 
 }
 
-impl State for Lbr {
-
-    fn feed(&self , input: char) -> Result<Update , Errors> {
+// the operators of OPERATOR_LIST except -> and => which are punctuation
+#[derive(Debug , Clone , Copy , PartialEq , Eq , Hash)]
+pub enum Op {
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Assign,
+    Bang,
+    Lt,
+    Gt,
+    Tilde,
+    Pipe,
+    Amp,
+    Caret,
+    Eq,
+    Ne,
+    Le,
+    Ge,
+    AndAnd,
+    OrOr,
+    StarStar,
+    Shl,
+    Shr,
+    PlusAssign,
+    MinusAssign,
+    StarAssign,
+    SlashAssign,
+    AmpAssign,
+    PipeAssign,
+    CaretAssign,
+    StarStarAssign,
+    ShlAssign,
+    ShrAssign,
+}
 
-        if input.is_whitespace() || input == '\n'  {
-            let new_state = Box::new(Blank {});
-            let token = self.get_token();
-            return Ok((new_state , Some(token)));
+impl Op {
+
+    pub const ALL: &'static [Op] = &[
+        Op::Plus,
+        Op::Minus,
+        Op::Star,
+        Op::Slash,
+        Op::Assign,
+        Op::Bang,
+        Op::Lt,
+        Op::Gt,
+        Op::Tilde,
+        Op::Pipe,
+        Op::Amp,
+        Op::Caret,
+        Op::Eq,
+        Op::Ne,
+        Op::Le,
+        Op::Ge,
+        Op::AndAnd,
+        Op::OrOr,
+        Op::StarStar,
+        Op::Shl,
+        Op::Shr,
+        Op::PlusAssign,
+        Op::MinusAssign,
+        Op::StarAssign,
+        Op::SlashAssign,
+        Op::AmpAssign,
+        Op::PipeAssign,
+        Op::CaretAssign,
+        Op::StarStarAssign,
+        Op::ShlAssign,
+        Op::ShrAssign,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Op::Plus => "+",
+            Op::Minus => "-",
+            Op::Star => "*",
+            Op::Slash => "/",
+            Op::Assign => "=",
+            Op::Bang => "!",
+            Op::Lt => "<",
+            Op::Gt => ">",
+            Op::Tilde => "~",
+            Op::Pipe => "|",
+            Op::Amp => "&",
+            Op::Caret => "^",
+            Op::Eq => "==",
+            Op::Ne => "!=",
+            Op::Le => "<=",
+            Op::Ge => ">=",
+            Op::AndAnd => "&&",
+            Op::OrOr => "||",
+            Op::StarStar => "**",
+            Op::Shl => "<<",
+            Op::Shr => ">>",
+            Op::PlusAssign => "+=",
+            Op::MinusAssign => "-=",
+            Op::StarAssign => "*=",
+            Op::SlashAssign => "/=",
+            Op::AmpAssign => "&=",
+            Op::PipeAssign => "|=",
+            Op::CaretAssign => "^=",
+            Op::StarStarAssign => "**=",
+            Op::ShlAssign => "<<=",
+            Op::ShrAssign => ">>=",
         }
-        
-        let new_state = (Blank {}).feed(input).unwrap().0;
-        let token = self.get_token();
-        return Ok((new_state , Some(token)));
-
     }
 
-    fn get_token(&self) -> Token {
-        Token::LBR
-    }
+}
+
+impl fmt::Display for Op {
 
-    fn get_type(&self) -> String {
-        String::from("Lbr")
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.as_str())
     }
 
-    fn get_value(&self) -> String {
-        String::from("{")
+}
+
+impl FromStr for Op {
+
+    type Err = UnknownToken;
+
+    fn from_str(text: &str) -> Result<Op , UnknownToken> {
+        Op::ALL.iter()
+            .find(|op| op.as_str() == text)
+            .copied()
+            .ok_or_else(|| UnknownToken(String::from(text)))
     }
 
 }
 
-impl State for Rbr {
+fn is_operator(input: &char) -> bool {
+    ['+', '-' , '=' , '/' , '*' , '!' , '<' , '>' , '~' , '|' , '&' , '^'].contains(input)
+}
 
-    fn feed(&self , input: char) -> Result<Update , Errors> {
+// every operator the language has , an operator token is the longest one that matches
+// the list is closed under prefixes so the machine can grow an operator one character at a time
+// -> and => are here too but they become punctuation tokens
+const OPERATOR_LIST: &[&str] = &[
+    "+" , "-" , "*" , "/" , "=" , "!" , "<" , ">" , "~" , "|" , "&" , "^",
+    "==" , "!=" , "<=" , ">=" , "&&" , "||" , "**" , "<<" , ">>",
+    "+=" , "-=" , "*=" , "/=" , "&=" , "|=" , "^=" , "->" , "=>",
+    "**=" , "<<=" , ">>=",
+];
+
+fn is_paren(input: &char) -> bool {
+    ['(' ,')' , '{' , '}'].contains(input)
+}
 
-        if input.is_whitespace() || input == '\n'  {
-            let new_state = Box::new(Blank {});
-            let token = self.get_token();
-            return Ok((new_state , Some(token)));
-        }
-        
-        let new_state = (Blank {}).feed(input).unwrap().0;
-        let token = self.get_token();
-        return Ok((new_state , Some(token)));
+fn is_punctuation(input: &char) -> bool {
+    [',' , ';' , '[' , ']' , '.' , ':'].contains(input)
+}
 
-    }
+// the characters that end an ident and start a new token
+fn ends_token(input: &char) -> bool {
+    is_operator(input) || is_paren(input) || is_punctuation(input) || input.is_whitespace()
+}
 
-    fn get_token(&self) -> Token {
-        Token::RBR
-    }
+// the characters that can come right after a number literal
+// a . is part of the number (or an error) so it doesn't end it
+fn ends_number(input: &char) -> bool {
+    *input != '.' && ends_token(input)
+}
 
-    fn get_type(&self) -> String {
-        String::from("Rbr")
-    }
+// type suffixes like 10i64 or 2f32 , the suffix picks between a Number and a Float token
+const NUMBER_SUFFIXES: &[&str] = &["i8" , "i16" , "i32" , "i64" , "u8" , "u16" , "u32" , "u64" , "f32" , "f64"];
 
-    fn get_value(&self) -> String {
-        String::from("}")
+// the digits of a number literal without its _ separators , only copied if it has any
+fn without_separators(digits: &str) -> Cow<'_ , str> {
+    match digits.contains('_') {
+        true => Cow::Owned(digits.replace('_' , "")),
+        false => Cow::Borrowed(digits),
     }
-
 }
 
-struct Operator {
-    value: String,
+// the biggest value an integer literal with the suffix can have
+fn suffix_max(suffix: &str) -> Option<u64> {
+    match suffix {
+        "i8" => Some(i8::MAX as u64),
+        "i16" => Some(i16::MAX as u64),
+        "i32" => Some(i32::MAX as u64),
+        "i64" => Some(i64::MAX as u64),
+        "u8" => Some(u8::MAX as u64),
+        "u16" => Some(u16::MAX as u64),
+        "u32" => Some(u32::MAX as u64),
+        "u64" => Some(u64::MAX),
+        _ => None,
+    }
 }
 
-impl Operator {
-    fn new(value: String) -> Operator {
-        Operator { value }
-    }       
+// the keywords of the language , a machine can be given its own list (see ImagineMachine::keywords)
+pub const KEYWORD_LIST: &[&str] = &[
+    "if" , "else" , "while" , "for" , "fn" , "let" , "return" , "true" , "false" , "nil" , "break" , "continue",
+];
+
+// represents the state of the current token being processed
+// the text of the token lives in the buffer of the machine so states never allocate
+// get_type is used for debugging and testing
+
+// Number , Float , Ident and Operator keep the token text in the buffer
+// the number states keep the literal as written , with its prefix , separators and suffix
+// the paren and punctuation states wait for the next character before emitting their token
+// the Text states keep the string with its escapes already applied
+// and the comment states keep the whole comment including the delimiters
+#[derive(Debug , Clone , Copy , PartialEq , Eq , Hash)]
+pub enum State {
+    Blank,
+    // decimal digits and _ separators
+    Number,
+    // a number after a 0x , 0b or 0o prefix
+    RadixNumber { radix: u32 },
+    // a number with a decimal point like 3.14 or .5
+    Float,
+    // a . that is either a DOT token or the start of a float like .5
+    Dot,
+    // the e of 1e9 , the sign of 1e-9 and the digits of the exponent
+    ExponentStart,
+    ExponentSign,
+    Exponent,
+    // a type suffix like i64
+    NumberSuffix,
+    Ident,
+    // the operator read so far , always one of OPERATOR_LIST
+    Operator,
+    Lpar,
+    Rpar,
+    Lbr,
+    Rbr,
+    Comma,
+    Semi,
+    Lsqb,
+    Rsqb,
+    // a : that may become a ::
+    Colon,
+    DColon,
+    // strings can span multiple lines
+    Text,
+    // the character right after a \ in a string
+    TextEscape,
+    // a \u{...} escape , open is set once the { is seen
+    // code holds the value of the hex digits read so far (at most 6 of them)
+    TextUnicode { open: bool , code: u32 , digits: u8 },
+    // the closing " was seen , the token is emitted with the next character
+    TextEnd,
+    // a // comment , it ends at the end of the line
+    LineComment,
+    // a /* */ comment , they can be nested so depth counts the comments still open
+    // last is the previous character if it may start a /* or */ pair
+    BlockComment { depth: usize , last: Option<char> },
+    // the closing */ was seen , the token is emitted with the next character
+    CommentEnd,
 }
 
-impl State for Operator {
+impl State {
+
+    pub fn get_type(&self) -> &'static str {
+        match self {
+            State::Blank => "Blank",
+            State::Number => "Number",
+            State::RadixNumber { .. } => "RadixNumber",
+            State::Float => "Float",
+            State::Dot => "Dot",
+            State::ExponentStart => "ExponentStart",
+            State::ExponentSign => "ExponentSign",
+            State::Exponent => "Exponent",
+            State::NumberSuffix => "NumberSuffix",
+            State::Ident => "Ident",
+            State::Operator => "Operator",
+            State::Lpar => "Lpar",
+            State::Rpar => "Rpar",
+            State::Lbr => "Lbr",
+            State::Rbr => "Rbr",
+            State::Comma => "Comma",
+            State::Semi => "Semi",
+            State::Lsqb => "Lsqb",
+            State::Rsqb => "Rsqb",
+            State::Colon => "Colon",
+            State::DColon => "DColon",
+            State::Text => "Text",
+            State::TextEscape => "TextEscape",
+            State::TextUnicode { .. } => "TextUnicode",
+            State::TextEnd => "TextEnd",
+            State::LineComment => "LineComment",
+            State::BlockComment { .. } => "BlockComment",
+            State::CommentEnd => "CommentEnd",
+        }
+    }
 
-    fn feed(&self , input: char) -> Result<Update , Errors> {
+    // the state the Blank state transitions to when it gets the input
+    // this is also where every token starts
+    fn start(input: char) -> Result<State , LexErrorKind> {
 
-        if is_operator(&input) {
-            let new_operator = format!("{}{}" , self.value , input);
-            let new_state = Box::new(Operator::new(new_operator));
-            return Ok((new_state , None));
+        if input.is_ascii_digit() {
+            return Ok(State::Number);
         }
 
-        if input.is_whitespace() || input == '\n'  {
-            let new_state = Box::new(Blank {});
-            let token = self.get_token();
-            return Ok((new_state , Some(token)));
+        if input.is_alphabetic() || input == '_' {
+            return Ok(State::Ident);
         }
-        
-        let new_state = (Blank {}).feed(input).unwrap().0;
-        let token = self.get_token();
-        return Ok((new_state , Some(token)));
 
-    }
+        if input.is_whitespace() {
+            return Ok(State::Blank);
+        }
 
-    fn get_token(&self) -> Token {
-        Token::Operator(self.value.clone())
-    }
+        if is_operator(&input) {
+            return Ok(State::Operator);
+        }
 
-    fn get_type(&self) -> String {
-        String::from("Operator")
-    }
+        match input {
+            '.' => Ok(State::Dot),
+            '"' => Ok(State::Text),
+            '(' => Ok(State::Lpar),
+            ')' => Ok(State::Rpar),
+            '{' => Ok(State::Lbr),
+            '}' => Ok(State::Rbr),
+            ',' => Ok(State::Comma),
+            ';' => Ok(State::Semi),
+            '[' => Ok(State::Lsqb),
+            ']' => Ok(State::Rsqb),
+            ':' => Ok(State::Colon),
+            _ => Err(LexErrorKind::UnexpectedCharacter),
+        }
 
-    fn get_value(&self) -> String {
-        self.value.clone()
     }
 
 }
 
-// idents are varible names or keywords
-struct Ident {
-    value: String,
-    is_keyword: bool,
-}
+// this trait represnts the behaviour of a state machine and is used in the lexer
+// it starts with a Blank state then feeds input (1 character) to the current state
+// and then it transitions to the new state returned from the previous state
+// and returns a token if the previous state emitted a token
 
-impl Ident {
-    fn new(value: String , is_keyword: bool) -> Ident {
-        Ident { value , is_keyword }
-    }
-}
+// example:
 
-impl State for Ident {
-    
-    fn feed(&self , input: char) -> Result<Update , Errors> {
+// '1' -> |Blank| -> |Number|
+// '2' -> |Number| -> |Number|
+// ' ' -> |Number| -> |Blank| and a Token::number(12) 
 
-        // idents can state with only alphabetic characters (see the blank state)
-        // and can contain _ or alphanumerical characters
-        if input.is_alphanumeric() || input == '_' {
+// see https://en.wikipedia.org/wiki/Finite-state_machine for more detail
 
-            let new_value = format!("{}{}" , self.value , input);
+// pos is the position of the input character in the source text
+// the machine uses it to give every token it emits a span
 
-            if KEYWORD_LIST.contains(&new_value.as_str()) {
-                let new_state = Box::new(Ident::new(new_value , true));
-                return Ok((new_state , None));
-            }
+pub trait Machine {
+    fn get_final_token(&self , end: Position) -> Result<SpannedToken , Errors>;
+    fn feed(&mut self , input: char , pos: Position) -> Result<Option<SpannedToken> , Errors>; 
+
+    // drops the token being built and goes back to the starting state
+    // returns the dropped text and where it started , if there was any
+    fn reset(&mut self) -> Option<(String , Position)>;
+
+    // a machine that looks ahead (see dfa::DfaMachine) can read past the end of its token
+    // and find out only later that a shorter token matched
+    // it then gives back the characters after that token and the lexer feeds them to it again
+    // this is asked for after every feed and flush
+    fn unread(&mut self) -> Vec<(char , Position)> {
+        Vec::new()
+    }
 
-            let new_state = Box::new(Ident::new(new_value , false)); 
-            return Ok((new_state , None));
+    // called when the input ends , before get_final_token
+    // a machine that read past the end of its token emits the token here and gives back the rest
+    fn flush(&mut self) -> Result<Option<SpannedToken> , Errors> {
+        Ok(None)
+    }
 
-        }
+    // whether a token can start with the input
+    // a lexer that recovers from errors keeps a complete token that is followed by a character
+    // that can't start one , like the x of x@ , and only makes the character an error
+    fn can_start(&self , _input: char) -> bool {
+        true
+    }
+}
 
-        if is_operator(&input) {
+// an implementation of the Machine trait
+// buffer holds the text of the token being processed , it is reused for every token
+// start is where the token being processed began
+// comments are skipped unless emit_comments is set , tools that need them can keep them as tokens
+// integer literals that don't fit in an i64 are an error unless big_integers is set
+// then they become a Token::BigInt
+// keywords are the idents that become a Token::Keyword , KEYWORD_LIST unless changed
+// trace is called with every step of feed when set (see ImagineMachine::trace)
+pub struct ImagineMachine {
+    state: State,
+    buffer: String,
+    start: Position,
+    emit_comments: bool,
+    big_integers: bool,
+    keywords: Vec<String>,
+    trace: Option<TraceHook>,
+}
 
-            let new_state = Box::new(Operator::new(String::from(input)));
-            let token = self.get_token();
-            return Ok((new_state , Some(token)));
+// what ImagineMachine::trace calls with every transition
+type TraceHook = Box<dyn FnMut(&Transition)>;
+
+// one step of the machine , the state it was in , the input it got , the state it moved to
+// and the token it emitted if it did
+// token is the token before comments are hidden so a trace shows them either way
+// on error the machine stays where it was so to is the same as from
+#[derive(Debug , Clone , PartialEq)]
+pub struct Transition {
+    pub from: State,
+    pub input: char,
+    pub to: State,
+    pub token: Option<Token>,
+    pub error: Option<LexErrorKind>,
+}
 
-        }
+// Number --'1'--> Number
+// Number --' '--> Blank emits Number(12)
+impl fmt::Display for Transition {
 
-        if ['(' ,')' , '{' , '}'].contains(&input) {
+    fn fmt(&self , f: &mut fmt::Formatter) -> fmt::Result {
 
-            let new_state = (Blank {}).feed(input).unwrap().0;
-            let token = self.get_token();
-            return Ok((new_state , Some(token)))
+        write!(f , "{} --{:?}--> {}" , self.from.get_type() , self.input , self.to.get_type())?;
 
+        if let Some(token) = &self.token {
+            write!(f , " emits {:?}" , token)?;
         }
-        
-        if input.is_whitespace() || input == '\n'  {
-            let new_state = Box::new(Blank {});
-            let token = self.get_token();
-            return Ok((new_state , Some(token)));
+
+        if let Some(error) = &self.error {
+            write!(f , " fails with {}" , error)?;
         }
 
-        Err(Errors::SyntaxError)
+        Ok(())
 
     }
 
-    fn get_token(&self) -> Token {
+}
 
-        // while mabey not the best solution it works
+impl Default for ImagineMachine {
+    fn default() -> ImagineMachine {
+        ImagineMachine::new()
+    }
+}
 
-        if self.is_keyword {
-            return Token::Keyword(self.value.clone());
-        }
+impl ImagineMachine {
 
-        Token::Ident(self.value.clone())
+    pub fn new() -> ImagineMachine {
+        ImagineMachine {
+            state: State::Blank,
+            buffer: String::new(),
+            start: Position::default(),
+            emit_comments: false,
+            big_integers: false,
+            keywords: KEYWORD_LIST.iter().map(|keyword| keyword.to_string()).collect(),
+            trace: None,
+        }
+    }
 
+    pub fn emit_comments(mut self , emit_comments: bool) -> ImagineMachine {
+        self.emit_comments = emit_comments;
+        self
     }
 
-    fn get_type(&self) -> String {
-        if self.is_keyword {String::from("Keyword")} else {String::from("Ident")}
+    pub fn big_integers(mut self , big_integers: bool) -> ImagineMachine {
+        self.big_integers = big_integers;
+        self
     }
 
-    fn get_value(&self) -> String {
-        self.value.clone()
+    // replaces the keywords , an empty list makes every word an ident
+    pub fn keywords<I , K>(mut self , keywords: I) -> ImagineMachine
+    where I: IntoIterator<Item = K> , K: Into<String> {
+        self.keywords = keywords.into_iter().map(Into::into).collect();
+        self
     }
 
-}
+    // adds keywords to the ones the machine already has , like the words of an embedded language
+    pub fn add_keywords<I , K>(mut self , keywords: I) -> ImagineMachine
+    where I: IntoIterator<Item = K> , K: Into<String> {
+        self.keywords.extend(keywords.into_iter().map(Into::into));
+        self
+    }
 
-// used for float numbers like 3.14
-struct Float {
-    value: String,
-}
+    // calls the hook with every transition the machine makes , to see what the lexer is doing
+    //
+    // let steps = Rc::new(RefCell::new(vec![]));
+    // let recorder = steps.clone();
+    // let machine = ImagineMachine::new().trace(move |step| recorder.borrow_mut().push(step.clone()));
+    pub fn trace<F>(mut self , hook: F) -> ImagineMachine
+    where F: FnMut(&Transition) + 'static {
+        self.trace = Some(Box::new(hook));
+        self
+    }
 
-impl Float {
-    fn new(value: String) -> Float {
-        Float {value}
+    pub fn get_state(&self) -> State {
+        self.state
     }
-}
 
-impl State for Float {
+    // same as State::get_type but tells keywords apart from idents
+    pub fn get_type(&self) -> &'static str {
 
-    fn feed(&self , input: char) -> Result<Update , Errors> {
-        
-        if input.is_numeric() {
-            let new_value = format!("{}{}" , self.value , input);
-            let new_state = Box::new(Float::new(new_value)); 
-            return Ok((new_state , None));
+        if self.state == State::Ident && self.is_keyword() {
+            return "Keyword";
         }
 
-        if input.is_whitespace() || input == '\n'  {
-            let new_state = Box::new(Blank {});
-            let token = self.get_token();
-            return Ok((new_state , Some(token)));
-        }
+        self.state.get_type()
 
-        Err(Errors::SyntaxError)
+    }
 
+    // the text of the token being processed , used for debugging and testing
+    pub fn get_value(&self) -> &str {
+        &self.buffer
     }
 
-    fn get_token(&self) -> Token {
-        Token::Float(self.value.parse::<f32>().unwrap())
+    fn is_keyword(&self) -> bool {
+        self.keywords.contains(&self.buffer)
     }
 
-    fn get_value(&self) -> String {
-        self.value.clone()
+    fn is_hidden(&self , token: &Token) -> bool {
+        matches!(token , Token::Comment(_)) && !self.emit_comments
+    }
+
+    // splits a number literal into its radix , its digits without the prefix and its suffix
+    // the digits are a slice of the buffer so they still have their separators
+    fn number_parts(&self) -> (u32 , &str , &str) {
+
+        let (literal , suffix) = match self.state {
+            // a suffix is a letter followed by digits so it starts at the last letter
+            State::NumberSuffix => {
+                let start = self.buffer.rfind(|input: char| input.is_ascii_alphabetic()).unwrap();
+                self.buffer.split_at(start)
+            }
+            _ => (self.buffer.as_str() , ""),
+        };
+
+        let (radix , digits) = match literal.get(..2) {
+            Some("0x") => (16 , &literal[2..]),
+            Some("0b") => (2 , &literal[2..]),
+            Some("0o") => (8 , &literal[2..]),
+            _ => (10 , literal),
+        };
+
+        (radix , digits , suffix)
+
     }
 
-    fn get_type(&self) -> String {
-        String::from("Float")
+    fn is_float_literal(&self) -> bool {
+
+        let (radix , digits , suffix) = self.number_parts();
+
+        suffix.starts_with('f') || (radix == 10 && digits.contains(['.' , 'e' , 'E']))
+
     }
 
-}
+    // the value of an integer literal if it fits in a u64
+    // the separators are skipped while reading so nothing is copied
+    fn integer_value(&self) -> Option<u64> {
 
-// used for i32 numbers
-#[derive(PartialEq , Debug)]
-struct Number {
-    value: String,
-}
+        let (radix , digits , _) = self.number_parts();
 
-impl Number {
+        digits
+            .chars()
+            .filter(|&input| input != '_')
+            .try_fold(0u64 , |value , input| value.checked_mul(radix as u64)?.checked_add(input.to_digit(radix)? as u64))
 
-    fn new(value: String) -> Number {
-        Number {value}
     }
 
-}
+    // a literal with an f32 suffix gets the precision of an f32
+    fn float_value(&self) -> f64 {
 
-impl State for Number {
+        let (_ , digits , suffix) = self.number_parts();
+        let digits = without_separators(digits);
 
-    fn feed(&self , input: char) -> Result<Update , Errors> {
-        
-        if input.is_numeric() {
-            let new_value = format!("{}{}" , self.value , input);
-            let new_state = Box::new(Number::new(new_value)); 
-            return Ok((new_state , None));
+        if suffix == "f32" {
+            return digits.parse::<f32>().unwrap() as f64;
         }
 
-        if input == '.' {
-            let new_value = format!("{}." , self.value);
-            let new_state = Box::new(Float::new(new_value)); 
-            return Ok((new_state , None));
-        }
+        digits.parse::<f64>().unwrap()
 
-        if ['(' ,')' , '{' , '}'].contains(&input) {
+    }
 
-            let new_state = (Blank {}).feed(input).unwrap().0;
-            let token = self.get_token();
-            return Ok((new_state , Some(token)))
+    fn number_token(&self) -> Token {
 
+        if self.is_float_literal() {
+            return Token::Float(self.float_value());
         }
-        
-        if input.is_whitespace() || input == '\n'  {
-            let new_state = Box::new(Blank {});
-            let token = self.get_token();
-            return Ok((new_state , Some(token)));
+
+        // check_end makes sure the value fits
+        match self.integer_value().and_then(|value| i64::try_from(value).ok()) {
+            Some(value) => Token::Number(value),
+            None => {
+                let (radix , digits , _) = self.number_parts();
+                Token::BigInt(BigInt::from_str_radix(&without_separators(digits) , radix).unwrap())
+            }
         }
 
-        if input.is_whitespace() || input == '\n'  {
-            let new_state = Box::new(Blank {});
-            let token = self.get_token();
-            return Ok((new_state , Some(token)));
+    }
+
+    // makes sure a complete number literal fits in the token it becomes
+    fn check_number(&self) -> Result<() , LexErrorKind> {
+
+        if self.is_float_literal() {
+
+            if self.float_value().is_infinite() {
+                return Err(LexErrorKind::FloatOverflow);
+            }
+
+            return Ok(());
+
+        }
+
+        let (_ , _ , suffix) = self.number_parts();
+        let value = self.integer_value();
+
+        let fits_suffix = match suffix_max(suffix) {
+            Some(max) => value.is_some_and(|value| value <= max),
+            None => true,
+        };
+
+        let fits_number = value.is_some_and(|value| value <= i64::MAX as u64);
+
+        if !fits_suffix || (!fits_number && !self.big_integers) {
+            return Err(LexErrorKind::IntegerOverflow);
         }
 
-        Err(Errors::SyntaxError)
+        Ok(())
 
     }
 
+    // builds the token of the current state out of the buffer
     fn get_token(&self) -> Token {
-        Token::Number(self.value.parse::<i32>().unwrap())
+
+        match self.state {
+            State::Blank => Token::Blank,
+            State::Number
+            | State::RadixNumber { .. }
+            | State::Float
+            | State::Exponent
+            | State::NumberSuffix => self.number_token(),
+            State::Dot => Token::DOT,
+            // these can't end a token (see check_end)
+            State::ExponentStart
+            | State::ExponentSign => Token::Blank,
+            // the keywords are all words (see ImagineMachine::keywords) so they always parse
+            State::Ident if self.is_keyword() => Token::Keyword(self.buffer.parse().unwrap()),
+            State::Ident => Token::Ident(self.buffer.clone()),
+            State::Operator if self.buffer == "->" => Token::RARROW,
+            State::Operator if self.buffer == "=>" => Token::FATARROW,
+            // the buffer is always in OPERATOR_LIST
+            State::Operator => Token::Operator(self.buffer.parse().unwrap()),
+            State::Lpar => Token::LPAR,
+            State::Rpar => Token::RPAR,
+            State::Lbr => Token::LBR,
+            State::Rbr => Token::RBR,
+            State::Comma => Token::COMMA,
+            State::Semi => Token::SEMI,
+            State::Lsqb => Token::LSQB,
+            State::Rsqb => Token::RSQB,
+            State::Colon => Token::COLON,
+            State::DColon => Token::DCOLON,
+            State::Text
+            | State::TextEscape
+            | State::TextUnicode { .. }
+            | State::TextEnd => Token::Text(self.buffer.clone()),
+            State::LineComment
+            | State::BlockComment { .. }
+            | State::CommentEnd => Token::Comment(self.buffer.clone()),
+        }
+
     }
 
-    fn get_type(&self) -> String {
-        return String::from("Number");
+    // moves to the state and starts its buffer with the input if the state needs it
+    fn enter(&mut self , state: State , input: char) {
+
+        self.state = state;
+
+        if !matches!(state , State::Blank | State::Text) {
+            self.buffer.push(input);
+        }
+
     }
 
-    // only used for testing
-    fn get_value(&self) -> String {
-        return self.value.clone();
+    fn next(&mut self , state: State) -> Result<Option<Token> , LexErrorKind> {
+        self.state = state;
+        Ok(None)
     }
 
-}
+    fn append(&mut self , input: char) -> Result<Option<Token> , LexErrorKind> {
+        self.buffer.push(input);
+        Ok(None)
+    }
 
-struct Blank;
+    fn append_and_next(&mut self , input: char , state: State) -> Result<Option<Token> , LexErrorKind> {
+        self.buffer.push(input);
+        self.next(state)
+    }
 
-// this state represnts the nothing state and can be used to transition into every other state
-impl State for Blank {
+    // _ separators can only come right after a digit or another separator
+    fn append_separator(&mut self , radix: u32) -> Result<Option<Token> , LexErrorKind> {
 
-    fn feed(&self , input: char) -> Result<Update , Errors> {
-        
-        if input.is_numeric() {
-            let state = Box::new(Number::new(input.to_string())); 
-            return Ok((state , None));
+        if !self.buffer.ends_with(|last: char| last.is_digit(radix) || last == '_') {
+            return Err(LexErrorKind::MalformedNumber(NumberError::MisplacedSeparator));
         }
 
-        if input.is_alphabetic() || input == '_' {
-            let state = Box::new(Ident::new(input.to_string() , false)); 
-            return Ok((state , None));
+        self.append('_')
+
+    }
+
+    // the input is the first letter of a type suffix
+    fn start_suffix(&mut self , input: char) -> Result<Option<Token> , LexErrorKind> {
+
+        if self.buffer.ends_with('_') {
+            return Err(LexErrorKind::MalformedNumber(NumberError::MisplacedSeparator));
         }
 
-        if input.is_whitespace() || input == '\n'  {
-            let state = Box::new(Blank {});
-            return Ok((state , None));
+        let float = matches!(self.state , State::Float | State::Exponent);
+        let radix = matches!(self.state , State::RadixNumber { .. });
+
+        if float && (input == 'i' || input == 'u') {
+            return Err(LexErrorKind::MalformedNumber(NumberError::IntegerSuffixOnFloat));
         }
 
-        if is_operator(&input) {
-            let state = Box::new(Operator::new(String::from(input)));
-            return Ok((state , None));
+        if radix && input == 'f' {
+            return Err(LexErrorKind::MalformedNumber(NumberError::FloatSuffixOnRadix));
         }
 
-        if input == '(' {
-            let state = Box::new(Lpar);
-            return Ok((state , None));
+        if !NUMBER_SUFFIXES.iter().any(|suffix| suffix.starts_with(input)) {
+            return Err(LexErrorKind::MalformedNumber(NumberError::InvalidSuffix));
         }
 
-        if input == ')' {
-            let state = Box::new(Rpar);
-            return Ok((state , None));
+        self.append_and_next(input , State::NumberSuffix)
+
+    }
+
+    // the transitions shared by the states that end with the digits of a number
+    // after a digit a number can end , go on with a suffix or fail
+    fn number_end(&mut self , input: char) -> Result<Option<Token> , LexErrorKind> {
+
+        if ends_number(&input) {
+            return self.finish(input);
         }
 
-        if input == '{' {
-            let state = Box::new(Lbr);
-            return Ok((state , None));
+        // 1.2.3 or 0x1.5 , there is no method call syntax on number literals
+        if input == '.' {
+            return Err(LexErrorKind::MalformedNumber(NumberError::UnexpectedDecimalPoint));
         }
 
-        if input == '}' {
-            let state = Box::new(Rbr);
-            return Ok((state , None));
+        if input.is_alphabetic() {
+            return self.start_suffix(input);
         }
 
-        Err(Errors::SyntaxError)
+        Err(LexErrorKind::UnexpectedCharacter)
 
     }
 
-    fn get_token(&self) -> Token {
-        Token::Blank
+    // makes sure the token being processed can end here
+    fn check_end(&self) -> Result<() , LexErrorKind> {
+
+        let last = self.buffer.chars().last();
+
+        match self.state {
+            State::Text | State::TextEscape | State::TextUnicode { .. } => Err(LexErrorKind::UnterminatedLiteral),
+            State::BlockComment { .. } => Err(LexErrorKind::UnterminatedComment),
+            State::ExponentStart | State::ExponentSign => Err(LexErrorKind::MalformedNumber(NumberError::MissingExponent)),
+            State::RadixNumber { .. } if self.buffer.len() == 2 => Err(LexErrorKind::MalformedNumber(NumberError::MissingDigits)),
+            State::Number
+            | State::RadixNumber { .. }
+            | State::Float
+            | State::Exponent if last == Some('_') => Err(LexErrorKind::MalformedNumber(NumberError::MisplacedSeparator)),
+            State::NumberSuffix if !NUMBER_SUFFIXES.contains(&self.number_parts().2) => {
+                Err(LexErrorKind::MalformedNumber(NumberError::InvalidSuffix))
+            }
+            State::Number
+            | State::RadixNumber { .. }
+            | State::Float
+            | State::Exponent
+            | State::NumberSuffix => self.check_number(),
+            _ => Ok(()),
+        }
+
     }
 
-    fn get_type(&self) -> String {
-        return String::from("Blank");
+    // emits the token of the current state and lets the Blank state handle the input
+    // nothing changes if the token can't end here or the Blank state rejects the input
+    fn finish(&mut self , input: char) -> Result<Option<Token> , LexErrorKind> {
+
+        self.check_end()?;
+        let state = State::start(input)?;
+        let token = self.get_token();
+
+        self.buffer.clear();
+        self.enter(state , input);
+
+        Ok(Some(token))
+
     }
 
-}
+    // input handling and transition and token emitting logic for every state
+    // a state either accepts the input , emits its token or returns an error
+    // on error the machine is left as it was
+    fn transition(&mut self , input: char) -> Result<Option<Token> , LexErrorKind> {
 
-// this trait represnts the behaviour of a state machine and is used in the lexer
-// it starts with a Blank state then feeds input (1 character) to the current state
-// and then it transitions to the new state returned from the previous state
-// and returns a token if the previous state emitted a token
+        match self.state {
 
-// example:
+            State::Blank => {
+                let state = State::start(input)?;
+                self.enter(state , input);
+                Ok(None)
+            }
 
-// '1' -> |Blank| -> |Number|
-// '2' -> |Number| -> |Number|
-// ' ' -> |Number| -> |Blank| and a Token::number(12) 
+            State::Number => {
 
-// see https://en.wikipedia.org/wiki/Finite-state_machine for more detail
+                if input.is_ascii_digit() {
+                    return self.append(input);
+                }
 
-pub trait Machine {
-    fn get_final_token(&self) -> Token;
-    fn feed(&mut self , input: char) -> Result<Option<Token> , Errors>; 
-}
+                if input == '_' {
+                    return self.append_separator(10);
+                }
 
-// an implementation of the Machine trait
-pub struct ImagineMachine {
-    current_state: Box<dyn State>,
-}
+                let radix = match input {
+                    'x' => 16,
+                    'b' => 2,
+                    'o' => 8,
+                    _ => 0,
+                };
 
-impl ImagineMachine {
+                if radix != 0 && self.buffer == "0" {
+                    return self.append_and_next(input , State::RadixNumber { radix });
+                }
+
+                if (input == '.' || input == 'e' || input == 'E') && self.buffer.ends_with('_') {
+                    return Err(LexErrorKind::MalformedNumber(NumberError::MisplacedSeparator));
+                }
+
+                if input == '.' {
+                    return self.append_and_next(input , State::Float);
+                }
+
+                if input == 'e' || input == 'E' {
+                    return self.append_and_next(input , State::ExponentStart);
+                }
+
+                self.number_end(input)
+
+            }
+
+            State::RadixNumber { radix } => {
+
+                if input.is_digit(radix) {
+                    return self.append(input);
+                }
+
+                if input == '_' {
+                    return self.append_separator(radix);
+                }
+
+                // a digit that is too big for the radix like 0b2 or 0o9
+                if input.is_ascii_digit() {
+                    return Err(LexErrorKind::MalformedNumber(NumberError::InvalidDigit));
+                }
+
+                if self.buffer.len() == 2 && !ends_number(&input) {
+                    return Err(LexErrorKind::MalformedNumber(NumberError::MissingDigits));
+                }
+
+                self.number_end(input)
+
+            }
+
+            State::Float => {
+
+                if input.is_ascii_digit() {
+                    return self.append(input);
+                }
+
+                if input == '_' {
+                    return self.append_separator(10);
+                }
+
+                if (input == 'e' || input == 'E') && self.buffer.ends_with('_') {
+                    return Err(LexErrorKind::MalformedNumber(NumberError::MisplacedSeparator));
+                }
+
+                if input == 'e' || input == 'E' {
+                    return self.append_and_next(input , State::ExponentStart);
+                }
+
+                self.number_end(input)
+
+            }
+
+            State::Dot => {
+
+                if input.is_ascii_digit() {
+                    return self.append_and_next(input , State::Float);
+                }
+
+                self.finish(input)
+
+            }
+
+            State::ExponentStart => {
+
+                if input == '+' || input == '-' {
+                    return self.append_and_next(input , State::ExponentSign);
+                }
+
+                if input.is_ascii_digit() {
+                    return self.append_and_next(input , State::Exponent);
+                }
+
+                Err(LexErrorKind::MalformedNumber(NumberError::MissingExponent))
+
+            }
+
+            State::ExponentSign => {
+
+                if input.is_ascii_digit() {
+                    return self.append_and_next(input , State::Exponent);
+                }
+
+                Err(LexErrorKind::MalformedNumber(NumberError::MissingExponent))
+
+            }
+
+            State::Exponent => {
+
+                if input.is_ascii_digit() {
+                    return self.append(input);
+                }
+
+                if input == '_' {
+                    return self.append_separator(10);
+                }
+
+                self.number_end(input)
+
+            }
+
+            State::NumberSuffix => {
+
+                if ends_number(&input) {
+                    return self.finish(input);
+                }
+
+                let (_ , _ , suffix) = self.number_parts();
+
+                if input.is_alphanumeric() || input == '_' {
+
+                    // the suffix so far followed by the input has to start one of the suffixes
+                    let continues = |valid: &&str| valid.strip_prefix(suffix).is_some_and(|rest| rest.starts_with(input));
+
+                    if !NUMBER_SUFFIXES.iter().any(continues) {
+                        return Err(LexErrorKind::MalformedNumber(NumberError::InvalidSuffix));
+                    }
+
+                    return self.append(input);
+
+                }
+
+                Err(LexErrorKind::UnexpectedCharacter)
+
+            }
+
+            State::Ident => {
+
+                // idents can state with only alphabetic characters (see State::start)
+                // and can contain _ or alphanumerical characters
+                if input.is_alphanumeric() || input == '_' {
+                    return self.append(input);
+                }
+
+                if ends_token(&input) {
+                    return self.finish(input);
+                }
+
+                Err(LexErrorKind::UnexpectedCharacter)
+
+            }
+
+            State::Operator => {
+
+                // a / followed by another / or a * starts a comment instead
+                if self.buffer == "/" && input == '/' {
+                    return self.append_and_next(input , State::LineComment);
+                }
+
+                if self.buffer == "/" && input == '*' {
+                    return self.append_and_next(input , State::BlockComment { depth: 1 , last: None });
+                }
+
+                // maximal munch , the operator only grows while it is still in the list
+                // so =-1 is = followed by -1 and !!x is ! ! x
+                self.buffer.push(input);
+                let known = OPERATOR_LIST.contains(&self.buffer.as_str());
+                self.buffer.pop();
+
+                if known {
+                    return self.append(input);
+                }
+
+                self.finish(input)
+
+            }
+
+            State::Colon => {
+
+                if input == ':' {
+                    return self.append_and_next(input , State::DColon);
+                }
+
+                self.finish(input)
+
+            }
+
+            State::Lpar
+            | State::Rpar
+            | State::Lbr
+            | State::Rbr
+            | State::Comma
+            | State::Semi
+            | State::Lsqb
+            | State::Rsqb
+            | State::DColon
+            | State::TextEnd
+            | State::CommentEnd => self.finish(input),
+
+            State::Text => match input {
+                '"' => self.next(State::TextEnd),
+                '\\' => self.next(State::TextEscape),
+                _ => self.append(input),
+            }
+
+            State::TextEscape => match input {
+                'n' => self.append_and_next('\n' , State::Text),
+                't' => self.append_and_next('\t' , State::Text),
+                '\\' => self.append_and_next('\\' , State::Text),
+                '"' => self.append_and_next('"' , State::Text),
+                'u' => self.next(State::TextUnicode { open: false , code: 0 , digits: 0 }),
+                _ => Err(LexErrorKind::InvalidEscape),
+            }
+
+            State::TextUnicode { open: false , .. } => {
+
+                if input == '{' {
+                    return self.next(State::TextUnicode { open: true , code: 0 , digits: 0 });
+                }
+
+                Err(LexErrorKind::InvalidEscape)
+
+            }
+
+            State::TextUnicode { open: true , code , digits } => {
+
+                if let Some(digit) = input.to_digit(16) {
+
+                    if digits == 6 {
+                        return Err(LexErrorKind::InvalidEscape);
+                    }
+
+                    return self.next(State::TextUnicode { open: true , code: code * 16 + digit , digits: digits + 1 });
+
+                }
+
+                if input == '}' && digits > 0 {
+                    let escaped = char::from_u32(code).ok_or(LexErrorKind::InvalidEscape)?;
+                    return self.append_and_next(escaped , State::Text);
+                }
+
+                Err(LexErrorKind::InvalidEscape)
+
+            }
+
+            State::LineComment => {
+
+                if input == '\n' {
+                    return self.finish(input);
+                }
+
+                self.append(input)
+
+            }
+
+            State::BlockComment { depth , last } => {
+
+                if last == Some('*') && input == '/' {
+
+                    if depth == 1 {
+                        return self.append_and_next(input , State::CommentEnd);
+                    }
+
+                    return self.append_and_next(input , State::BlockComment { depth: depth - 1 , last: None });
+
+                }
+
+                if last == Some('/') && input == '*' {
+                    return self.append_and_next(input , State::BlockComment { depth: depth + 1 , last: None });
+                }
+
+                self.append_and_next(input , State::BlockComment { depth , last: Some(input) })
+
+            }
+
+        }
 
-    pub fn new() -> ImagineMachine {
-        ImagineMachine {current_state: Box::new(Blank {})}
     }
 
 }
 
 impl Machine for ImagineMachine {
 
-    fn get_final_token(&self) -> Token {
-        self.current_state.get_token()
+    fn get_final_token(&self , end: Position) -> Result<SpannedToken , Errors> {
+
+        // an unfinished token is reported where it started
+        if let Err(kind) = self.check_end() {
+            return Err(Errors::SyntaxError(LexError {
+                kind,
+                found: None,
+                state: String::from(self.get_type()),
+                position: self.start,
+            }));
+        }
+
+        let token = self.get_token();
+
+        if self.is_hidden(&token) {
+            return Ok(SpannedToken { token: Token::Blank , span: Span::new(end , end) });
+        }
+
+        Ok(SpannedToken { token , span: Span::new(self.start , end) })
+
     }
 
-    fn feed(&mut self , input: char) -> Result<Option<Token> , Errors> {
+    fn feed(&mut self , input: char , pos: Position) -> Result<Option<SpannedToken> , Errors> {
 
-        let update = self.current_state.feed(input);
+        let was_blank = self.state == State::Blank;
+        let from = self.state;
+        let token = self.transition(input);
+
+        if let Some(trace) = &mut self.trace {
+            trace(&Transition {
+                from,
+                input,
+                to: self.state,
+                token: token.as_ref().ok().cloned().flatten(),
+                error: token.as_ref().err().cloned(),
+            });
+        }
 
-        match update {
-            Ok((new_state , token)) => {
-                self.current_state = new_state;
-                return Ok(token);
-            } 
-            Err(err) => Err(err)
+        let token = token.map_err(|kind| {
+            Errors::SyntaxError(LexError {
+                kind,
+                found: Some(input),
+                state: String::from(self.get_type()),
+                position: pos,
+            })
+        })?;
+
+        // the token ends right before the character that made the state emit it
+        let token = token.map(|token| SpannedToken { token , span: Span::new(self.start , pos) });
+
+        // a new token starts at this character if the machine was idle
+        // or the previous token just ended
+        if token.is_some() || was_blank {
+            self.start = pos;
         }
+
+        Ok(token.filter(|token| !self.is_hidden(&token.token)))
         
     }
 
+    fn can_start(&self , input: char) -> bool {
+        State::start(input).is_ok()
+    }
+
+    fn reset(&mut self) -> Option<(String , Position)> {
+
+        let state = self.state;
+        let value = self.buffer.clone();
+
+        self.state = State::Blank;
+        self.buffer.clear();
+
+        if state == State::Blank {
+            return None;
+        }
+
+        Some((value , self.start))
+
+    }
+
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
 
     use super::*;
 
+    // feeds one character to the machine and moves pos past it
+    fn feed(machine: &mut ImagineMachine , pos: &mut Position , input: char) -> Option<Token> {
+        let token = machine.feed(input , *pos).unwrap();
+        *pos = pos.advance(input);
+        token.map(|token| token.token)
+    }
+
+    // feeds the whole text to a new machine and returns every token
+    fn run(text: &str) -> Result<Vec<Token> , Errors> {
+
+        let mut machine = ImagineMachine::new();
+        let mut pos = Position::default();
+        let mut tokens = vec![];
+
+        for input in text.chars() {
+            if let Some(token) = machine.feed(input , pos)? {
+                tokens.push(token.token);
+            }
+            pos = pos.advance(input);
+        }
+
+        tokens.push(machine.get_final_token(pos)?.token);
+
+        Ok(tokens)
+
+    }
+
+    // feeds the text to a new machine without finishing the last token
+    fn machine_with(text: &str) -> ImagineMachine {
+
+        let mut machine = ImagineMachine::new();
+        let mut pos = Position::default();
+
+        for input in text.chars() {
+            feed(&mut machine , &mut pos , input);
+        }
+
+        machine
+
+    }
+
+    fn error_of(result: Result<Vec<Token> , Errors>) -> LexError {
+        match result.unwrap_err() {
+            Errors::SyntaxError(error) => error,
+            error => panic!("unexpected error {:?}" , error),
+        }
+    }
+
     #[test]
     fn blank_to_number() {
 
-        let state = Blank {};
-        let (new_state , _) = state.feed('1').unwrap();
+        let machine = machine_with("1");
 
-        assert_eq!(new_state.get_type() , String::from("Number"));
-        assert_eq!(new_state.get_value() , String::from("1"));
+        assert_eq!(machine.get_type() , "Number");
+        assert_eq!(machine.get_value() , "1");
 
     }
 
     #[test]
     fn number_to_number() {
 
-        let mut state: Box<dyn State> = Box::new(Number::new(String::from("1")));
+        let mut machine = machine_with("1");
+        let mut pos = Position::default();
         
         for i in 2..6 {
-
-            let (new_state , _) = state.feed(char::from_digit(i, 10).unwrap()).unwrap();
-            state = new_state;
-
+            feed(&mut machine , &mut pos , char::from_digit(i, 10).unwrap());
         }
 
-        assert_eq!(state.get_type() , String::from("Number"));
-        assert_eq!(state.get_value() , String::from("12345"));
+        assert_eq!(machine.get_type() , "Number");
+        assert_eq!(machine.get_value() , "12345");
 
     }
 
@@ -563,48 +1486,43 @@ mod tests {
     #[should_panic]
     fn number_syntax_error() {
 
-        let state = Number::new(String::from("1"));
-        state.feed('a').unwrap();
+        let mut machine = machine_with("1");
+        machine.feed('a' , Position::default()).unwrap();
 
     }
 
     #[test]
     fn number_token() {
 
-        let state = Number::new(String::from("12")); 
-        let (_ , token) = state.feed(' ').unwrap();
+        let mut machine = machine_with("12");
+        let token = machine.feed(' ' , Position::default()).unwrap();
 
-        assert_eq!(token.unwrap() , Token::Number(12));
+        assert_eq!(token.unwrap().token , Token::Number(12));
 
     }
 
     #[test]
     fn number_to_float() {
 
-        let mut state: Box<dyn State> = Box::new(Number::new(String::from("3")));
-        state = state.feed('.').unwrap().0;
-        state = state.feed('1').unwrap().0;
-        state = state.feed('4').unwrap().0;
+        let machine = machine_with("3.14");
 
-        assert_eq!(state.get_type() , "Float");
-        assert_eq!(state.get_value() , "3.14");
+        assert_eq!(machine.get_type() , "Float");
+        assert_eq!(machine.get_value() , "3.14");
 
     }
 
     #[test]
     fn float_token() {
 
-        let mut state: Box<dyn State> = Box::new(Number::new(String::from("3")));
-        state = state.feed('.').unwrap().0;
-        state = state.feed('1').unwrap().0;
-        let (new_state , mut token) = state.feed('4').unwrap();
-        state = new_state;
+        let mut machine = machine_with("3.1");
+        let mut pos = Position::default();
 
+        let token = feed(&mut machine , &mut pos , '4');
         assert!(token.is_none());
     
-        (state , token) = state.feed(' ').unwrap();
+        let token = feed(&mut machine , &mut pos , ' ');
         
-        assert_eq!(state.get_type() , "Blank");
+        assert_eq!(machine.get_type() , "Blank");
         assert_eq!(token.unwrap() , Token::Float(3.14));
 
     }
@@ -612,28 +1530,57 @@ mod tests {
     #[test]
     fn ident_test() {
 
-        let mut state: Box<dyn State> = Box::new(Ident::new(String::from("p") , false));
-        state = state.feed('l').unwrap().0;
-        state = state.feed('a').unwrap().0;
-        state = state.feed('y').unwrap().0;
-
-        let token = state.get_token();
+        let machine = machine_with("play");
         
-        assert_eq!(state.get_type() , "Ident");
-        assert_eq!(token , Token::Ident(String::from("play")));
+        assert_eq!(machine.get_type() , "Ident");
+        assert_eq!(machine.get_token() , Token::Ident(String::from("play")));
 
     }
 
     #[test]
     fn keyword_test() {
 
-        let mut state: Box<dyn State> = Box::new(Ident::new(String::from("i") , false));
-        state = state.feed('f').unwrap().0;
+        let machine = machine_with("if");
 
-        let token = state.get_token();
+        assert_eq!(machine.get_type() , "Keyword");
+        assert_eq!(machine.get_token() , Token::Keyword(Keyword::If));
 
-        assert_eq!(state.get_type() , "Keyword");
-        assert_eq!(token , Token::Keyword(String::from("if")));
+    }
+
+    #[test]
+    fn keyword_list() {
+
+        let mut machine = ImagineMachine::new().add_keywords(["on" , "spawn"]);
+        let mut pos = Position::default();
+        let mut tokens = vec![];
+
+        for input in "while on spawn emit ".chars() {
+            tokens.extend(feed(&mut machine , &mut pos , input));
+        }
+
+        assert_eq!(tokens , vec![
+            Token::Keyword(Keyword::While),
+            Token::Keyword(Keyword::Custom(String::from("on"))),
+            Token::Keyword(Keyword::Custom(String::from("spawn"))),
+            Token::Ident(String::from("emit")),
+        ]);
+
+        let mut machine = ImagineMachine::new().keywords(vec![String::from("emit")]);
+        let mut tokens = vec![];
+
+        for input in "if emit ".chars() {
+            tokens.extend(feed(&mut machine , &mut pos , input));
+        }
+
+        assert_eq!(tokens , vec![
+            Token::Ident(String::from("if")),
+            Token::Keyword(Keyword::Custom(String::from("emit"))),
+        ]);
+
+        let mut machine = ImagineMachine::new().keywords(Vec::<String>::new());
+        feed(&mut machine , &mut pos , 'i');
+        feed(&mut machine , &mut pos , 'f');
+        assert_eq!(machine.get_type() , "Ident");
 
     }
 
@@ -641,81 +1588,499 @@ mod tests {
     fn machine_test() {
 
         let mut machine = ImagineMachine::new();
+        let mut pos = Position::default();
 
-        let mut token = machine.feed('7').unwrap();
+        let mut token = feed(&mut machine , &mut pos , '7');
         assert!(token.is_none());
 
-        token = machine.feed('2').unwrap();
+        token = feed(&mut machine , &mut pos , '2');
         assert!(token.is_none());      
         
-        token = machine.feed(' ').unwrap();
+        token = feed(&mut machine , &mut pos , ' ');
         assert_eq!(token.unwrap() , Token::Number(72));
 
-        token = machine.feed('3').unwrap();
+        token = feed(&mut machine , &mut pos , '3');
         assert!(token.is_none());
 
-        token = machine.feed('.').unwrap();
+        token = feed(&mut machine , &mut pos , '.');
         assert!(token.is_none());
 
-        token = machine.feed('1').unwrap();
+        token = feed(&mut machine , &mut pos , '1');
         assert!(token.is_none());
 
-        token = machine.feed('4').unwrap();
+        token = feed(&mut machine , &mut pos , '4');
         assert!(token.is_none());
 
-        token = machine.feed(' ').unwrap();
+        token = feed(&mut machine , &mut pos , ' ');
         assert_eq!(token.unwrap() , Token::Float(3.14));
 
-        token = machine.feed('a').unwrap();
+        token = feed(&mut machine , &mut pos , 'a');
         assert!(token.is_none());
 
-        token = machine.feed('b').unwrap();
+        token = feed(&mut machine , &mut pos , 'b');
         assert!(token.is_none());
 
-        token = machine.feed('c').unwrap();
+        token = feed(&mut machine , &mut pos , 'c');
         assert!(token.is_none());
 
-        token = machine.feed(' ').unwrap();
+        token = feed(&mut machine , &mut pos , ' ');
         assert_eq!(token.unwrap() , Token::Ident(String::from("abc")));
 
         // only test one keyword
 
-        token = machine.feed('i').unwrap();
+        token = feed(&mut machine , &mut pos , 'i');
         assert!(token.is_none());
 
-        token = machine.feed('f').unwrap();
+        token = feed(&mut machine , &mut pos , 'f');
         assert!(token.is_none());
 
-        token = machine.feed(' ').unwrap();
-        assert_eq!(token.unwrap() , Token::Keyword(String::from("if")));
+        token = feed(&mut machine , &mut pos , ' ');
+        assert_eq!(token.unwrap() , Token::Keyword(Keyword::If));
 
-        let token = machine.get_final_token();
+        let token = machine.get_final_token(pos).unwrap().token;
         assert_eq!(token , Token::Blank);
 
     }
 
+    #[test]
+    fn machine_error_test() {
+
+        let mut machine = ImagineMachine::new();
+        let mut pos = Position::default();
+
+        feed(&mut machine , &mut pos , '1');
+        feed(&mut machine , &mut pos , '2');
+
+        let error = machine.feed('x' , pos).unwrap_err();
+
+        assert_eq!(error , Errors::SyntaxError(LexError {
+            kind: LexErrorKind::MalformedNumber(NumberError::InvalidSuffix),
+            found: Some('x'),
+            state: String::from("Number"),
+            position: Position { offset: 2 , line: 1 , column: 3 },
+        }));
+
+    }
+
+    #[test]
+    fn number_overflow() {
+
+        assert_eq!(run("9223372036854775807").unwrap() , vec![Token::Number(i64::MAX)]);
+
+        // the literal could still become a float so the value is checked at its end
+        let mut machine = machine_with("922337203685477580");
+        assert_eq!(machine.feed('8' , Position::default()) , Ok(None));
+        assert_eq!(machine.get_value() , "9223372036854775808");
+
+        for text in ["9223372036854775808" , "128i8" , "256u8" , "0x1_0000_0000u32" , "18446744073709551616u64" , "0x8000000000000000"] {
+            assert_eq!(error_of(run(text)).kind , LexErrorKind::IntegerOverflow , "{}" , text);
+        }
+
+        assert_eq!(run("127i8 255u8 2147483648").unwrap() , vec![
+            Token::Number(127),
+            Token::Number(255),
+            Token::Number(2147483648),
+        ]);
+
+        assert_eq!(run("100000000000000000000.0").unwrap() , vec![Token::Float(1e20)]);
+
+    }
+
+    #[test]
+    fn float_precision() {
+
+        assert_eq!(run("0.1 0.1f32 1e300").unwrap() , vec![
+            Token::Float(0.1),
+            Token::Float(0.1f32 as f64),
+            Token::Float(1e300),
+        ]);
+
+        // an integer part that doesn't fit an i64 is fine in a float
+        assert_eq!(run("12345678901234567890e-5").unwrap() , vec![Token::Float(123456789012345.67)]);
+
+        assert_eq!(error_of(run("1e999")).kind , LexErrorKind::FloatOverflow);
+        assert_eq!(error_of(run("1e39f32")).kind , LexErrorKind::FloatOverflow);
+
+    }
+
+    #[test]
+    fn big_integers() {
+
+        let mut machine = ImagineMachine::new().big_integers(true);
+        let mut pos = Position::default();
+        let mut tokens = vec![];
+
+        for input in "123456789012345678901234567890 0xffff_ffff_ffff_ffff_ff 18446744073709551615u64 7".chars() {
+            if let Some(token) = machine.feed(input , pos).unwrap() {
+                tokens.push(token.token);
+            }
+            pos = pos.advance(input);
+        }
+
+        tokens.push(machine.get_final_token(pos).unwrap().token);
+
+        let big = |digits , radix| Token::BigInt(BigInt::from_str_radix(digits , radix).unwrap());
+
+        assert_eq!(tokens , vec![
+            big("123456789012345678901234567890" , 10),
+            big("ffffffffffffffffff" , 16),
+            big("18446744073709551615" , 10),
+            Token::Number(7),
+        ]);
+
+        // a suffix still limits the value
+        let mut machine = machine_with("300u8");
+        machine.big_integers = true;
+        assert!(machine.feed(' ' , pos).is_err());
+
+    }
+
+    #[test]
+    fn machine_span_test() {
+
+        let mut machine = ImagineMachine::new();
+        let mut pos = Position::default();
+        let mut tokens = vec![];
+
+        for input in "  ab(\n 12".chars() {
+            if let Some(token) = machine.feed(input , pos).unwrap() {
+                tokens.push(token);
+            }
+            pos = pos.advance(input);
+        }
+
+        tokens.push(machine.get_final_token(pos).unwrap());
+
+        let at = |offset , line , column| Position { offset , line , column };
+
+        assert_eq!(tokens , vec![
+            SpannedToken { token: Token::Ident(String::from("ab")) , span: Span::new(at(2 , 1 , 3) , at(4 , 1 , 5)) },
+            SpannedToken { token: Token::LPAR , span: Span::new(at(4 , 1 , 5) , at(5 , 1 , 6)) },
+            SpannedToken { token: Token::Number(12) , span: Span::new(at(7 , 2 , 2) , at(9 , 2 , 4)) },
+        ]);
+
+    }
+
     #[test]
     fn operator_test() {
 
         for character in "+-*/!<>!~|&^=".chars() {
-            let state = (Blank {}).feed(character).unwrap().0;
-            assert_eq!(Token::Operator(String::from(character)) , state.get_token())
+            let machine = machine_with(&character.to_string());
+            assert_eq!(Token::Operator(character.to_string().parse().unwrap()) , machine.get_token())
         }
 
     }
 
     #[test]
-    fn multy_character_oprator() {
+    fn operator_table() {
 
-        for character in "+-*/!<>!~|&^=".chars() {
-            for character2 in "+-*/!<>!~|&^".chars() {
-                let state = (Blank {}).feed(character).unwrap().0;
-                let state = state.feed(character2).unwrap().0;
-                let operator_string = String::from(format!("{}{}" , character , character2));
-                assert_eq!(Token::Operator(operator_string) , state.get_token());
+        for operator in OPERATOR_LIST {
+
+            // every prefix of an operator is an operator too
+            for end in 1..operator.len() {
+                assert!(OPERATOR_LIST.contains(&&operator[..end]) , "{}" , operator);
             }
+
+            if *operator == "->" || *operator == "=>" {
+                continue;
+            }
+
+            assert_eq!(run(operator).unwrap() , vec![Token::Operator(operator.parse().unwrap())]);
+
         }
 
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn typed_tokens() {
+
+        for op in Op::ALL {
+            assert_eq!(op.to_string().parse::<Op>().unwrap() , *op);
+            assert!(OPERATOR_LIST.contains(&op.as_str()));
+        }
+
+        for keyword in KEYWORD_LIST {
+            let parsed: Keyword = keyword.parse().unwrap();
+            assert!(!matches!(parsed , Keyword::Custom(_)) , "{}" , keyword);
+            assert_eq!(parsed.to_string() , *keyword);
+        }
+
+        assert_eq!("spawn".parse::<Keyword>().unwrap() , Keyword::Custom(String::from("spawn")));
+        assert_eq!("+ =".parse::<Op>() , Err(UnknownToken(String::from("+ ="))));
+        assert_eq!("->".parse::<Op>() , Err(UnknownToken(String::from("->"))));
+        assert!("two words".parse::<Keyword>().is_err());
+        assert!("".parse::<Keyword>().is_err());
+
+    }
+
+    #[test]
+    fn maximal_munch() {
+
+        let operators = |text: &str| -> Vec<String> {
+            run(text).unwrap().into_iter().filter_map(|token| match token {
+                Token::Operator(operator) => Some(operator.to_string()),
+                _ => None,
+            }).collect()
+        };
+
+        assert_eq!(operators("a=-1") , vec!["=" , "-"]);
+        assert_eq!(operators("!!x") , vec!["!" , "!"]);
+        assert_eq!(operators("a<=b>>=c**=d") , vec!["<=" , ">>=" , "**="]);
+        assert_eq!(operators("a===b") , vec!["==" , "="]);
+        assert_eq!(operators("x&&!y||-z") , vec!["&&" , "!" , "||" , "-"]);
+        assert_eq!(operators("a***b") , vec!["**" , "*"]);
+
+        assert_eq!(run("a=->b").unwrap() , vec![
+            Token::Ident(String::from("a")),
+            Token::Operator(Op::Assign),
+            Token::RARROW,
+            Token::Ident(String::from("b")),
+        ]);
+
+    }
+
+    #[test]
+    fn punctuation_test() {
+
+        assert_eq!(run("f(a , b); arr[0].x").unwrap() , vec![
+            Token::Ident(String::from("f")),
+            Token::LPAR,
+            Token::Ident(String::from("a")),
+            Token::COMMA,
+            Token::Ident(String::from("b")),
+            Token::RPAR,
+            Token::SEMI,
+            Token::Ident(String::from("arr")),
+            Token::LSQB,
+            Token::Number(0),
+            Token::RSQB,
+            Token::DOT,
+            Token::Ident(String::from("x")),
+        ]);
+
+        assert_eq!(run("a: int std::io fn(x)->y x=>1 [1,2.5]").unwrap() , vec![
+            Token::Ident(String::from("a")),
+            Token::COLON,
+            Token::Ident(String::from("int")),
+            Token::Ident(String::from("std")),
+            Token::DCOLON,
+            Token::Ident(String::from("io")),
+            Token::Keyword(Keyword::Fn),
+            Token::LPAR,
+            Token::Ident(String::from("x")),
+            Token::RPAR,
+            Token::RARROW,
+            Token::Ident(String::from("y")),
+            Token::Ident(String::from("x")),
+            Token::FATARROW,
+            Token::Number(1),
+            Token::LSQB,
+            Token::Number(1),
+            Token::COMMA,
+            Token::Float(2.5),
+            Token::RSQB,
+        ]);
+
+        // numbers end at operators and punctuation too
+        assert_eq!(run("1+2;3u8:4").unwrap() , vec![
+            Token::Number(1),
+            Token::Operator(Op::Plus),
+            Token::Number(2),
+            Token::SEMI,
+            Token::Number(3),
+            Token::COLON,
+            Token::Number(4),
+        ]);
+
+        assert_eq!(run(".").unwrap() , vec![Token::DOT]);
+
+    }
+
+    #[test]
+    fn text_test() {
+
+        assert_eq!(run(r#"x "hello world"(y)"#).unwrap() , vec![
+            Token::Ident(String::from("x")),
+            Token::Text(String::from("hello world")),
+            Token::LPAR,
+            Token::Ident(String::from("y")),
+            Token::RPAR,
+        ]);
+
+        assert_eq!(run("\"two\nlines\" \"\"").unwrap() , vec![
+            Token::Text(String::from("two\nlines")),
+            Token::Text(String::new()),
+        ]);
+
+    }
+
+    #[test]
+    fn text_escape_test() {
+
+        let tokens = run(r#""a\n\t\\\"b\u{41}\u{1F600}""#).unwrap();
+        assert_eq!(tokens , vec![Token::Text(String::from("a\n\t\\\"bA\u{1F600}"))]);
+
+    }
+
+    #[test]
+    fn text_errors() {
+
+        let error = error_of(run("x = \"open\nstring"));
+        assert_eq!(error.kind , LexErrorKind::UnterminatedLiteral);
+        assert_eq!(error.found , None);
+        assert_eq!(error.position , Position { offset: 4 , line: 1 , column: 5 });
+
+        let error = error_of(run(r#""bad \q""#));
+        assert_eq!(error.kind , LexErrorKind::InvalidEscape);
+        assert_eq!(error.found , Some('q'));
+        assert_eq!(error.state , "TextEscape");
+        assert_eq!(error.position.offset , 6);
+
+        for text in [r#""\u41""# , r#""\u{}""# , r#""\u{110000}""# , r#""\u{1234567}""# , r#""\u{4g}""#] {
+            assert_eq!(error_of(run(text)).kind , LexErrorKind::InvalidEscape);
+        }
+
+    }
+
+    #[test]
+    fn comment_test() {
+
+        let tokens = run("a // line comment\nb /* block /* nested */ still */c/d").unwrap();
+
+        assert_eq!(tokens , vec![
+            Token::Ident(String::from("a")),
+            Token::Ident(String::from("b")),
+            Token::Ident(String::from("c")),
+            Token::Operator(Op::Slash),
+            Token::Ident(String::from("d")),
+        ]);
+
+        assert_eq!(run("x // comment at the end").unwrap().last() , Some(&Token::Blank));
+
+        let error = error_of(run("x /* /* */"));
+        assert_eq!(error.kind , LexErrorKind::UnterminatedComment);
+        assert_eq!(error.position.offset , 2);
+
+    }
+
+    #[test]
+    fn comment_tokens() {
+
+        let mut machine = ImagineMachine::new().emit_comments(true);
+        let mut pos = Position::default();
+        let mut tokens = vec![];
+
+        for input in "// one\n/* two */x".chars() {
+            if let Some(token) = machine.feed(input , pos).unwrap() {
+                tokens.push(token);
+            }
+            pos = pos.advance(input);
+        }
+
+        assert_eq!(tokens[0].token , Token::Comment(String::from("// one")));
+        assert_eq!(tokens[1].token , Token::Comment(String::from("/* two */")));
+        assert_eq!((tokens[1].span.start.offset , tokens[1].span.end.offset) , (7 , 16));
+        assert_eq!(machine.get_final_token(pos).unwrap().token , Token::Ident(String::from("x")));
+
+    }
+
+    #[test]
+    fn number_literals() {
+
+        assert_eq!(run("0xFF 0x1f_ff 0b1010 0o17 1_000_000 007").unwrap() , vec![
+            Token::Number(255),
+            Token::Number(0x1fff),
+            Token::Number(10),
+            Token::Number(15),
+            Token::Number(1000000),
+            Token::Number(7),
+        ]);
+
+        assert_eq!(run("1e3 2.5E-2 1.e2 .5 1_0.2_5 3e+1").unwrap() , vec![
+            Token::Float(1000.0),
+            Token::Float(0.025),
+            Token::Float(100.0),
+            Token::Float(0.5),
+            Token::Float(10.25),
+            Token::Float(30.0),
+        ]);
+
+        assert_eq!(run("10i64 7u8 0xffi32 2f32 1.5f64 1e2f32").unwrap() , vec![
+            Token::Number(10),
+            Token::Number(7),
+            Token::Number(255),
+            Token::Float(2.0),
+            Token::Float(1.5),
+            Token::Float(100.0),
+        ]);
+
+    }
+
+    #[test]
+    fn malformed_numbers() {
+
+        let cases = [
+            ("0x " , NumberError::MissingDigits),
+            ("0xg" , NumberError::MissingDigits),
+            ("0b" , NumberError::MissingDigits),
+            ("0b102" , NumberError::InvalidDigit),
+            ("0o78" , NumberError::InvalidDigit),
+            ("1e" , NumberError::MissingExponent),
+            ("1e+ " , NumberError::MissingExponent),
+            ("2.5ex" , NumberError::MissingExponent),
+            ("1_ " , NumberError::MisplacedSeparator),
+            ("1_.5" , NumberError::MisplacedSeparator),
+            ("1._5" , NumberError::MisplacedSeparator),
+            ("1_e5" , NumberError::MisplacedSeparator),
+            ("0x_1" , NumberError::MisplacedSeparator),
+            ("1.2.3" , NumberError::UnexpectedDecimalPoint),
+            ("1e5.3" , NumberError::UnexpectedDecimalPoint),
+            ("0x1.5" , NumberError::UnexpectedDecimalPoint),
+            ("12ab" , NumberError::InvalidSuffix),
+            ("1i7 " , NumberError::InvalidSuffix),
+            ("1i" , NumberError::InvalidSuffix),
+            ("1.5i32" , NumberError::IntegerSuffixOnFloat),
+            ("1e3u8" , NumberError::IntegerSuffixOnFloat),
+            ("0b1f32" , NumberError::FloatSuffixOnRadix),
+        ];
+
+        for (text , expected) in cases {
+            assert_eq!(error_of(run(text)).kind , LexErrorKind::MalformedNumber(expected) , "{}" , text);
+        }
+
+        assert_eq!(error_of(run("0x8000_0000_0000_0000")).kind , LexErrorKind::IntegerOverflow);
+
+    }
+
+    #[test]
+    fn tracing() {
+
+        use std::{cell::RefCell , rc::Rc};
+
+        let steps = Rc::new(RefCell::new(vec![]));
+        let recorder = steps.clone();
+
+        let mut machine = ImagineMachine::new().trace(move |step: &Transition| recorder.borrow_mut().push(step.to_string()));
+        let mut pos = Position::default();
+
+        for input in "12 // x\n@".chars() {
+            let _ = machine.feed(input , pos);
+            pos = pos.advance(input);
+        }
+
+        assert_eq!(*steps.borrow() , [
+            "Blank --'1'--> Number",
+            "Number --'2'--> Number",
+            "Number --' '--> Blank emits Number(12)",
+            "Blank --'/'--> Operator",
+            "Operator --'/'--> LineComment",
+            "LineComment --' '--> LineComment",
+            "LineComment --'x'--> LineComment",
+            // the comment is hidden from the lexer but not from the trace
+            "LineComment --'\\n'--> Blank emits Comment(\"// x\")",
+            "Blank --'@'--> Blank fails with unexpected character",
+        ]);
+
+    }
+
+}
@@ -0,0 +1,557 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::{Stdout , Write},
+    rc::Rc,
+};
+
+use crate::{
+    bytecode::{Capture , Closure , CompiledFunction , Instruction , Upvalue},
+    compiler::compile,
+    interpreter::{Builtin , Value , binary , error , unary},
+    statemachine::{ImagineMachine , SpannedToken},
+    lexer::{ImagineLexer , Lexer},
+    parser::Parser,
+    common::{Errors , RuntimeErrorKind},
+};
+
+// a function that is running , base is the index of the function on the stack
+// and its arguments and locals follow it (see compiler::FunctionState)
+// closure is there if the function captured variables
+struct Frame {
+    function: Rc<CompiledFunction>,
+    closure: Option<Rc<Closure>>,
+    ip: usize,
+    base: usize,
+}
+
+// the calls a program can nest before it is stopped
+// the vm doesn't use the rust stack for calls so this can be much higher than in the interpreter
+const MAX_FRAMES: usize = 4096;
+
+// runs the bytecode of the compiler (see compiler::compile)
+// stack holds the locals and the temporary values of every running function
+// globals keeps the variables between runs so a REPL can build on what it ran before
+// open has the upvalues that still point at the stack with their slots , sorted by slot
+// so the ones to close when the stack shrinks are at the end
+// output is where print writes to
+pub struct Vm<W: Write = Stdout> {
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    globals: HashMap<Rc<str> , Value>,
+    open: Vec<(usize , Rc<RefCell<Upvalue>>)>,
+    output: W,
+}
+
+impl Default for Vm {
+    fn default() -> Vm {
+        Vm::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Vm {
+        Vm::with_output(std::io::stdout())
+    }
+}
+
+impl<W: Write> Vm<W> {
+
+    pub fn with_output(output: W) -> Vm<W> {
+
+        let mut globals = HashMap::new();
+        globals.insert(Rc::from(Builtin::Print.name()) , Value::Builtin(Builtin::Print));
+
+        Vm { stack: vec![] , frames: vec![] , globals , open: vec![] , output }
+
+    }
+
+    pub fn output(&self) -> &W {
+        &self.output
+    }
+
+    // lexes , parses , compiles and runs the source text
+    pub fn run_text(&mut self , text: &str) -> Result<Value , Errors> {
+        let tokens = ImagineLexer::new(String::from(text) , ImagineMachine::new()).lex()?;
+        self.run(tokens)
+    }
+
+    // parses , compiles and runs the tokens of a lexer
+    pub fn run(&mut self , tokens: Vec<SpannedToken>) -> Result<Value , Errors> {
+        let program = Parser::new(tokens).parse_program()?;
+        self.execute(compile(&program)?)
+    }
+
+    // runs the top level function of a program and returns what it returns
+    pub fn execute(&mut self , function: Rc<CompiledFunction>) -> Result<Value , Errors> {
+
+        self.stack.push(Value::Compiled(function.clone()));
+        self.frames.push(Frame { function , closure: None , ip: 0 , base: 0 });
+
+        let result = self.dispatch();
+
+        // an error leaves the frames of the failed calls behind
+        // and the closures they made may still point at their slots
+        self.close_upvalues(0);
+        self.stack.clear();
+        self.frames.clear();
+
+        result
+
+    }
+
+    fn pop(&mut self) -> Value {
+        // the compiler never pops more than it pushed
+        self.stack.pop().unwrap()
+    }
+
+    fn peek(&self) -> &Value {
+        self.stack.last().unwrap()
+    }
+
+    // the upvalue of the slot , made if no closure captured the slot yet
+    fn capture(&mut self , slot: usize) -> Rc<RefCell<Upvalue>> {
+
+        let index = self.open.partition_point(|(other , _)| *other < slot);
+
+        if let Some((other , upvalue)) = self.open.get(index) {
+            if *other == slot {
+                return upvalue.clone();
+            }
+        }
+
+        let upvalue = Rc::new(RefCell::new(Upvalue::Open(slot)));
+        self.open.insert(index , (slot , upvalue.clone()));
+
+        upvalue
+
+    }
+
+    // moves the values of the slots from the start on into the upvalues that point at them
+    fn close_upvalues(&mut self , start: usize) {
+
+        while self.open.last().is_some_and(|(slot , _)| *slot >= start) {
+            let (slot , upvalue) = self.open.pop().unwrap();
+            *upvalue.borrow_mut() = Upvalue::Closed(self.stack[slot].clone());
+        }
+
+    }
+
+    // the running closure's upvalue , the compiler only uses upvalues in closures
+    fn upvalue(&self , index: u16) -> Rc<RefCell<Upvalue>> {
+        self.frames.last().unwrap().closure.as_ref().unwrap().upvalues[index as usize].clone()
+    }
+
+    // a runtime error at the instruction that is running
+    fn error(&self , kind: RuntimeErrorKind) -> Errors {
+        let frame = self.frames.last().unwrap();
+        error(kind , frame.function.chunk.spans[frame.ip - 1])
+    }
+
+    // the name a global instruction refers to , the compiler only puts names there
+    fn name(&self , constant: u16) -> Rc<str> {
+        match &self.frames.last().unwrap().function.chunk.constants[constant as usize] {
+            Value::Str(name) => name.clone(),
+            value => unreachable!("{:?} is not a name" , value),
+        }
+    }
+
+    fn dispatch(&mut self) -> Result<Value , Errors> {
+
+        loop {
+
+            let frame = self.frames.last_mut().unwrap();
+            let instruction = frame.function.chunk.code[frame.ip];
+            let base = frame.base;
+            frame.ip += 1;
+
+            match instruction {
+
+                Instruction::Constant(constant) => {
+                    let value = frame.function.chunk.constants[constant as usize].clone();
+                    self.stack.push(value);
+                }
+
+                Instruction::Nil => self.stack.push(Value::Nil),
+                Instruction::True => self.stack.push(Value::Bool(true)),
+                Instruction::False => self.stack.push(Value::Bool(false)),
+
+                // the popped value may be a local a closure captured
+                Instruction::Pop => {
+                    self.close_upvalues(self.stack.len() - 1);
+                    self.pop();
+                }
+
+                Instruction::GetLocal(slot) => {
+                    let value = self.stack[base + slot as usize].clone();
+                    self.stack.push(value);
+                }
+
+                Instruction::SetLocal(slot) => {
+                    self.stack[base + slot as usize] = self.peek().clone();
+                }
+
+                Instruction::GetGlobal(constant) => {
+
+                    let name = self.name(constant);
+
+                    match self.globals.get(&name) {
+                        Some(value) => self.stack.push(value.clone()),
+                        None => return Err(self.error(RuntimeErrorKind::UndefinedVariable(name.to_string()))),
+                    }
+
+                }
+
+                Instruction::SetGlobal(constant) => {
+
+                    let name = self.name(constant);
+                    let value = self.peek().clone();
+
+                    match self.globals.get_mut(&name) {
+                        Some(slot) => *slot = value,
+                        None => return Err(self.error(RuntimeErrorKind::UndefinedVariable(name.to_string()))),
+                    }
+
+                }
+
+                Instruction::DefineGlobal(constant) => {
+                    let name = self.name(constant);
+                    let value = self.pop();
+                    self.globals.insert(name , value);
+                }
+
+                Instruction::GetUpvalue(index) => {
+
+                    let value = match &*self.upvalue(index).borrow() {
+                        Upvalue::Open(slot) => self.stack[*slot].clone(),
+                        Upvalue::Closed(value) => value.clone(),
+                    };
+
+                    self.stack.push(value);
+
+                }
+
+                Instruction::SetUpvalue(index) => {
+
+                    let value = self.peek().clone();
+
+                    match &mut *self.upvalue(index).borrow_mut() {
+                        Upvalue::Open(slot) => self.stack[*slot] = value,
+                        Upvalue::Closed(closed) => *closed = value,
+                    }
+
+                }
+
+                Instruction::Closure(constant) => {
+
+                    // the compiler only makes closures of functions
+                    let function = match &frame.function.chunk.constants[constant as usize] {
+                        Value::Compiled(function) => function.clone(),
+                        value => unreachable!("{:?} is not a function" , value),
+                    };
+
+                    let upvalues = function
+                        .upvalues
+                        .iter()
+                        .map(|capture| match *capture {
+                            Capture::Local(slot) => self.capture(base + slot as usize),
+                            Capture::Upvalue(index) => self.upvalue(index),
+                        })
+                        .collect();
+
+                    self.stack.push(Value::Closure(Rc::new(Closure { function , upvalues })));
+
+                }
+
+                Instruction::Unary(op) => {
+                    let operand = self.pop();
+                    let value = unary(op , operand).map_err(|kind| self.error(kind))?;
+                    self.stack.push(value);
+                }
+
+                Instruction::Binary(op) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let value = binary(op , left , right).map_err(|kind| self.error(kind))?;
+                    self.stack.push(value);
+                }
+
+                Instruction::ToBool => {
+                    let value = self.pop();
+                    self.stack.push(Value::Bool(value.is_truthy()));
+                }
+
+                Instruction::Jump(target) => frame.ip = target as usize,
+
+                Instruction::JumpIfFalse(target) => {
+                    if !self.pop().is_truthy() {
+                        self.frames.last_mut().unwrap().ip = target as usize;
+                    }
+                }
+
+                Instruction::Call(count) => self.call(count as usize)?,
+
+                Instruction::Return => {
+
+                    let value = self.pop();
+                    let frame = self.frames.pop().unwrap();
+
+                    self.close_upvalues(frame.base);
+                    self.stack.truncate(frame.base);
+
+                    if self.frames.is_empty() {
+                        return Ok(value);
+                    }
+
+                    self.stack.push(value);
+
+                }
+
+            }
+
+        }
+
+    }
+
+    // the function is on the stack right below its arguments
+    fn call(&mut self , count: usize) -> Result<() , Errors> {
+
+        let base = self.stack.len() - count - 1;
+
+        let (function , closure) = match &self.stack[base] {
+            Value::Compiled(function) => (function.clone() , None),
+            Value::Closure(closure) => (closure.function.clone() , Some(closure.clone())),
+            Value::Builtin(builtin) => {
+
+                let builtin = *builtin;
+                let args = self.stack.split_off(base + 1);
+                self.pop();
+
+                let value = builtin.call(&args , &mut self.output)?;
+                self.stack.push(value);
+
+                return Ok(());
+
+            }
+            other => return Err(self.error(RuntimeErrorKind::NotCallable(other.type_name()))),
+        };
+
+        if count != function.arity {
+            return Err(self.error(RuntimeErrorKind::WrongArgumentCount { expected: function.arity , found: count }));
+        }
+
+        if self.frames.len() == MAX_FRAMES {
+            return Err(self.error(RuntimeErrorKind::StackOverflow));
+        }
+
+        self.frames.push(Frame { function , closure , ip: 0 , base });
+
+        Ok(())
+
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{interpreter::Interpreter , common::RuntimeError};
+
+    // runs the program on the vm and the interpreter and checks they print the same
+    fn output_of(text: &str) -> String {
+
+        let mut vm = Vm::with_output(vec![]);
+        let value = vm.run_text(text).unwrap();
+
+        let mut interpreter = Interpreter::with_output(vec![]);
+        assert_eq!(interpreter.run_text(text).unwrap() , value);
+        assert_eq!(interpreter.output() , vm.output());
+
+        String::from_utf8(vm.output().clone()).unwrap()
+
+    }
+
+    fn error_of(text: &str) -> RuntimeError {
+        match Vm::with_output(vec![]).run_text(text).unwrap_err() {
+            Errors::RuntimeError(error) => error,
+            error => panic!("unexpected error {:?}" , error),
+        }
+    }
+
+    #[test]
+    fn expressions() {
+
+        assert_eq!(Vm::with_output(vec![]).run_text("1 + 2 * 3").unwrap() , Value::Int(7));
+        assert_eq!(Vm::with_output(vec![]).run_text("let x = 1").unwrap() , Value::Nil);
+
+        assert_eq!(output_of("
+            print(7 / 2 , 7 / 2.0 , 2 ** 10 , \"a\" + \"b\" , 1 == 1.0)
+            print(nil && x , 1 && nil , false || 2 , nil || nil , !0)
+        ") , "3 3.5 1024 ab true\nfalse false true false false\n");
+
+    }
+
+    #[test]
+    fn scopes_and_loops() {
+
+        assert_eq!(output_of("
+            let total = 0
+            let i = 0
+            while i < 10 {
+                let next = i + 1
+                i = next
+                if i / 2 * 2 == i { continue }
+                {
+                    let doubled = i * 2
+                    total += doubled
+                }
+                if i > 6 { break }
+            }
+            print(total , i)
+            {
+                let a = 1
+                { let a = 2 a += 1 print(a) }
+                print(a)
+            }
+        ") , "32 7\n3\n1\n");
+
+    }
+
+    #[test]
+    fn functions() {
+
+        assert_eq!(output_of("
+            fn fib(n) {
+                if n < 2 { return n }
+                return fib(n - 1) + fib(n - 2)
+            }
+
+            fn outer(x) {
+                fn twice(y) { return y * 2 }
+                let result = twice(x)
+                while true { return result + 1 }
+            }
+
+            fn nothing() {}
+
+            print(fib(20) , outer(4) , nothing())
+        ") , "6765 9 nil\n");
+
+    }
+
+    #[test]
+    fn closures() {
+
+        assert_eq!(output_of("
+            fn counter() {
+                let count = 0
+                fn next() {
+                    count += 1
+                    return count
+                }
+                return next
+            }
+
+            let a = counter()
+            let b = counter()
+            print(a() , a() , b())
+
+            fn outer(x) {
+                fn middle() {
+                    fn inner() { return x }
+                    x = x * 10
+                    return inner
+                }
+                return middle
+            }
+
+            print(outer(4)()())
+        ") , "1 2 1\n40\n");
+
+        // the functions of a block can call the ones declared after them and the one they are in
+        assert_eq!(output_of("
+            fn outer() { fn a() { return b() } fn b() { return 7 } return a() }
+            fn recursive() { fn f(n) { fn g() { return f(n - 1) } if n == 0 { return 5 } return g() } return f(3) }
+            print(outer() , recursive())
+            {
+                fn even(n) { if n == 0 { return true } return odd(n - 1) }
+                fn odd(n) { if n == 0 { return false } return even(n - 1) }
+                let x = 1
+                print(even(4) , odd(4) , x)
+            }
+        ") , "7 5\ntrue false 1\n");
+
+        // every pass of a loop gets its own variable , also when it leaves with a break
+        assert_eq!(output_of("
+            let first = nil
+            let second = nil
+            let i = 0
+            while true {
+                let n = i
+                fn get() { return n }
+                if i == 0 { first = get } else { second = get break }
+                i += 1
+            }
+            {
+                let shared = 1
+                fn set(value) { shared = value }
+                set(5)
+                print(first() , second() , shared)
+            }
+        ") , "0 1 5\n");
+
+    }
+
+    #[test]
+    fn deep_nesting() {
+
+        // code as deep as the parser allows compiles and runs on both on a 2MiB test thread
+        let depth = crate::parser::MAX_DEPTH - 4;
+
+        assert_eq!(output_of(&format!("print({}1{})" , "(".repeat(depth) , ")".repeat(depth))) , "1\n");
+        assert_eq!(output_of(&format!("print({}1)" , "-".repeat(depth))) , "1\n");
+        assert_eq!(output_of(&format!("fn f() {{ {}print(1){} }} f()" , "{".repeat(depth) , "}".repeat(depth))) , "1\n");
+
+        // an else if chain isn't nesting however long it is
+        let chain: String = (0..1000).map(|n| format!("if x == {} {{ print({}) }} else " , n , n)).collect();
+        assert_eq!(output_of(&format!("let x = 998 {}{{ print(-1) }}" , chain)) , "998\n");
+        assert_eq!(output_of(&format!("fn f(x) {{ {}{{ print(-1) }} }} f(1000)" , chain)) , "-1\n");
+
+    }
+
+    #[test]
+    fn globals_persist() {
+
+        let mut vm = Vm::with_output(vec![]);
+        vm.run_text("let x = 40 fn add(a) { return x + a }").unwrap();
+
+        assert_eq!(vm.run_text("add(2)").unwrap() , Value::Int(42));
+
+        // a failed run doesn't break the next one
+        assert!(vm.run_text("add(1 , 2)").is_err());
+        assert_eq!(vm.run_text("x += 1 add(1)").unwrap() , Value::Int(42));
+
+        // a closure made by a failed run keeps its variables
+        assert!(vm.run_text("let get = nil { let y = 7 fn f() { return y } get = f 1 / 0 }").is_err());
+        assert_eq!(vm.run_text("get()").unwrap() , Value::Int(7));
+
+    }
+
+    #[test]
+    fn runtime_errors() {
+
+        let error = error_of("let x = 1\nx + \"a\"");
+        assert_eq!(error.kind , RuntimeErrorKind::InvalidOperands { op: crate::statemachine::Op::Plus , left: "int" , right: "string" });
+        assert_eq!((error.span.start.line , error.span.start.column) , (2 , 1));
+
+        assert_eq!(error_of("y").kind , RuntimeErrorKind::UndefinedVariable(String::from("y")));
+        assert_eq!(error_of("y = 1").kind , RuntimeErrorKind::UndefinedVariable(String::from("y")));
+        assert_eq!(error_of("fn f() { return 1 / 0 } f()").kind , RuntimeErrorKind::DivisionByZero);
+        assert_eq!(error_of("1()").kind , RuntimeErrorKind::NotCallable("int"));
+        assert_eq!(error_of("fn f(a) {} f()").kind , RuntimeErrorKind::WrongArgumentCount { expected: 1 , found: 0 });
+        assert_eq!(error_of("fn f() { f() } f()").kind , RuntimeErrorKind::StackOverflow);
+
+    }
+
+}